@@ -1,27 +1,24 @@
-use std::iter;
-
-
 #[macro_use]
 extern crate criterion;
 
 use criterion::BenchmarkId;
 use criterion::Criterion;
-use criterion::Throughput;
 use criterion::black_box;
 use charsub::Scheduler;
 use hashbrown::HashMap;
 use rand::prelude::*;
 
-const CYCLES: &'static [usize] = &[1, 2, 4, 8, 16];
+const CYCLES: &[usize] = &[1, 2, 4, 8, 16];
 const MAX: usize = 16;
 
 macro_rules! test_me {
-    ($scheduler:ty, $pattern:ty) => 
+    ($scheduler:ty, $pattern:ty, $new:expr) => 
     (   
         
         paste::paste!{
         
         
+        #[allow(non_snake_case)]
         fn [<finished_ $scheduler _ $pattern>](c: &mut Criterion) {
             let mut group = c.benchmark_group(stringify!($scheduler));
 
@@ -35,7 +32,7 @@ macro_rules! test_me {
                             let mut buf: [u8; MAX] = [0; MAX];                            
                             rand::thread_rng().fill_bytes(&mut buf[..*in_size]);
 
-                            let mut executor: $scheduler = Default::default();
+                            let mut executor: $scheduler = $new;
                             executor.push(black_box(charsub::Cell::from(&buf[..*in_size])));
                             
                             let mut map = HashMap::new();
@@ -64,15 +61,16 @@ macro_rules! test_me {
 }
 
 use charsub::{
-    scheduler::{MultithreadMutex, TokioMutex, SingleThread},
-    ConstPattern, ModulusPattern
+    scheduler::{TokioMutex, SingleThread, WorkStealing},
+    ConstPattern
 
 };
 
-test_me!(SingleThread, ConstPattern);
-test_me!(TokioMutex, ConstPattern);
+test_me!(SingleThread, ConstPattern, SingleThread::new());
+test_me!(TokioMutex, ConstPattern, TokioMutex::new());
+test_me!(WorkStealing, ConstPattern, WorkStealing::new(0).unwrap());
 
-criterion_group!(benches, finished_SingleThread_ConstPattern, finished_TokioMutex_ConstPattern);
+criterion_group!(benches, finished_SingleThread_ConstPattern, finished_TokioMutex_ConstPattern, finished_WorkStealing_ConstPattern);
 
 
 criterion_main!(benches);
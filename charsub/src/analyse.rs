@@ -0,0 +1,239 @@
+use crate::{
+    Cell,
+    Rulebook,
+    RuleCell,
+    keyspace::Keyspace,
+    patterns::Handler
+};
+
+use std::fmt;
+
+use hashbrown::{HashMap, HashSet};
+
+/// How a single rule fared
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleStats {
+    /// the character the rule replaces
+    pub from: u8,
+    pub to: RuleCell,
+    /// known passwords found through it
+    pub hits: u64,
+    /// candidates it took part in
+    pub candidates: u128,
+}
+
+impl RuleStats {
+    pub fn candidates_per_hit(&self) -> Option<u128> {
+        (self.hits > 0).then(|| self.candidates / self.hits as u128)
+    }
+}
+
+/// Which rules turned seeds into known passwords.
+///
+/// A candidate is attributed every rule along the shortest chain
+/// turning each of its seed's characters into its own, so `a:4 4:A`
+/// both take part in `A` standing where the seed had `a`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub seeds: usize,
+    pub known: usize,
+    /// candidates every seed leads to together
+    pub candidates: u128,
+    /// known passwords found
+    pub hits: u64,
+    /// most hits first
+    pub rules: Vec<RuleStats>,
+    /// the rules which found each hit together, by the characters
+    /// they replace, with how many hits each set found. most hits first
+    pub combinations: Vec<(Vec<u8>, u64)>,
+}
+
+impl Report {
+    pub fn candidates_per_hit(&self) -> Option<u128> {
+        (self.hits > 0).then(|| self.candidates / self.hits as u128)
+    }
+}
+
+/// checks every seed's keyspace for `known` passwords,
+/// counting candidates without enumerating them
+pub fn analyse<H>(seeds: &[Cell], rules: &Rulebook, known: &HashSet<Cell>) -> Report
+where H: Handler
+{
+    let mut hits: HashMap<u8, u64> = HashMap::new();
+    let mut candidates: HashMap<u8, u128> = HashMap::new();
+    let mut combinations: HashMap<Vec<u8>, u64> = HashMap::new();
+    let mut found: HashSet<&Cell> = HashSet::new();
+    let mut total: u128 = 0;
+
+    let chains: HashMap<u8, HashMap<u8, Vec<u8>>> = rules.0.keys()
+        .map(|from| (*from, chains(*from, rules)))
+        .collect();
+    let chain = |from: u8, to: u8| -> &[u8] {
+        chains.get(&from).and_then(|x| x.get(&to)).map_or(&[], Vec::as_slice)
+    };
+
+    for seed in seeds {
+        let keyspace = Keyspace::new::<H>(seed, rules);
+        let size = keyspace.size();
+        total = total.saturating_add(size);
+
+        // candidates using a rule are all of them, but those where no position does
+        for from in rules.0.keys() {
+            let without = seed.iter()
+                .enumerate()
+                .map(|(idx, byte)| keyspace.reach(idx).iter()
+                    .filter(|to| !chain(*byte, **to).contains(from))
+                    .count() as u128)
+                .fold(1u128, u128::saturating_mul);
+            let entry = candidates.entry(*from).or_default();
+            *entry = entry.saturating_add(size.saturating_sub(without));
+        }
+
+        for item in known.iter().filter(|x| x.len() == seed.len()) {
+            if found.contains(item) || keyspace.index_of(item).is_none() {
+                continue
+            }
+            found.insert(item);
+
+            let mut used: Vec<u8> = seed.iter()
+                .zip(item.iter())
+                .flat_map(|(from, to)| chain(*from, *to).iter().copied())
+                .collect();
+            used.sort_unstable();
+            used.dedup();
+
+            for from in &used {
+                *hits.entry(*from).or_default() += 1;
+            }
+            *combinations.entry(used).or_default() += 1;
+        }
+    }
+
+    let mut rules: Vec<RuleStats> = rules.0.iter()
+        .map(|(from, entry)| RuleStats {
+            from: *from,
+            to: RuleCell::from(entry.clone()),
+            hits: hits.get(from).copied().unwrap_or(0),
+            candidates: candidates.get(from).copied().unwrap_or(0),
+        })
+        .collect();
+    rules.sort_unstable_by(|x, y| y.hits.cmp(&x.hits)
+        .then(x.candidates.cmp(&y.candidates))
+        .then(x.from.cmp(&y.from)));
+
+    let mut combinations: Vec<(Vec<u8>, u64)> = combinations.into_iter().collect();
+    combinations.sort_unstable_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(&y.0)));
+
+    Report {
+        seeds: seeds.len(),
+        known: known.len(),
+        candidates: total,
+        hits: found.len() as u64,
+        rules,
+        combinations,
+    }
+}
+
+/// the rules, by the character they replace, along the shortest
+/// chain from `byte` to each character it turns into
+pub(crate) fn chains(byte: u8, rules: &Rulebook) -> HashMap<u8, Vec<u8>> {
+    let mut found: HashMap<u8, Vec<u8>> = HashMap::new();
+    found.insert(byte, Vec::new());
+    let mut order = vec![byte];
+
+    let mut idx = 0;
+    while let Some(from) = order.get(idx).copied() {
+        if let Some(entry) = rules.0.get(&from) {
+            for to in RuleCell::from(entry.clone()) {
+                if !found.contains_key(&to) {
+                    let mut chain = found[&from].clone();
+                    chain.push(from);
+                    found.insert(to, chain);
+                    order.push(to);
+                }
+            }
+        }
+        idx += 1;
+    }
+    found
+}
+
+fn escape(bytes: &[u8]) -> String {
+    bytes.iter().flat_map(|x| std::ascii::escape_default(*x)).map(char::from).collect()
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let per_hit = |x: Option<u128>| x.map_or("-".to_string(), |x| x.to_string());
+
+        writeln!(f, "Seeds: {}, known passwords: {}, candidates: {}", self.seeds, self.known, self.candidates)?;
+        writeln!(f, "Hits: {}, candidates per hit: {}", self.hits, per_hit(self.candidates_per_hit()))?;
+
+        writeln!(f)?;
+        writeln!(f, "{:<16} {:>8} {:>8} {:>16} {:>16}", "rule", "hits", "rate", "candidates", "per hit")?;
+        for rule in &self.rules {
+            let name = format!("{}:{}", escape(&[rule.from]), escape(&rule.to));
+            let rate = match self.hits {
+                0 => 0.0,
+                hits => rule.hits as f64 / hits as f64 * 100.0
+            };
+            writeln!(
+                f, "{:<16} {:>8} {:>7.1}% {:>16} {:>16}",
+                name, rule.hits, rate, rule.candidates, per_hit(rule.candidates_per_hit())
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "{:<16} {:>8}", "combination", "hits")?;
+        for (rules, hits) in &self.combinations {
+            let name = match rules.is_empty() {
+                true => "(seed)".to_string(),
+                false => rules.iter().map(|x| escape(&[*x])).collect::<Vec<_>>().join("+")
+            };
+            writeln!(f, "{:<16} {:>8}", name, hits)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Generator,
+        patterns::ConstPattern,
+        scheduler::SingleThread,
+        testutil::rulebook
+    };
+
+    #[test]
+    fn behavior_analyse() {
+        let rules = rulebook(&[(b'a', b"4"), (b'4', b"A"), (b's', b"5$"), (b'x', b"%")]);
+        let seeds = vec![Cell::from(&b"pass"[..]), Cell::from(&b"as"[..])];
+        let known: HashSet<Cell> = ["p4ss", "pA5s", "pass", "p4$$", "45", "nope"].iter()
+            .map(|x| Cell::from(x.as_bytes()))
+            .collect();
+
+        let report = analyse::<ConstPattern>(&seeds, &rules, &known);
+        assert_eq!(report.candidates, 3 * 3 * 3 + 3 * 3);
+        assert_eq!(report.hits, 5);
+
+        let stats = |from: u8| report.rules.iter().find(|x| x.from == from).unwrap().clone();
+        assert_eq!((stats(b'a').hits, stats(b'4').hits, stats(b's').hits, stats(b'x').hits), (4, 1, 3, 0));
+        assert_eq!(stats(b'x').candidates, 0);
+        assert_eq!(stats(b'x').candidates_per_hit(), None);
+
+        // count what the generator actually finds using each rule
+        let mut gen = Generator::new(rules.clone(), SingleThread::new());
+        gen.seed(&b"pass"[..]);
+        for _ in 0..6 {
+            gen.new_generation::<ConstPattern>().unwrap();
+        }
+        let using_s = gen.into_set().iter().filter(|x| x[2] != b's' || x[3] != b's').count() as u128;
+        assert_eq!(stats(b's').candidates, using_s + 3 * 2);
+
+        assert_eq!(report.combinations[0], (vec![b'a', b's'], 2));
+        assert!(report.combinations.contains(&(Vec::new(), 1)));
+        assert!(report.to_string().contains("a+s"));
+    }
+}
@@ -1,31 +1,352 @@
 use crate::{
     Cell, RuleCell,
-    patterns::RuleEntry,
-    cursor::{Cursor, Output},
-    scheduler::Scheduler
+    patterns::{RuleEntry, Filter},
+    scheduler::Scheduler,
+    storage::Cells,
+    keyspace::{Keyspace, Shard},
+    progress::{Progress, GenerationStats},
+    cancel::CancelToken,
+    order::Order,
+    policy::{Policy, Pruner},
+    markov::Model,
+    closure::{Closure, Hops},
+    patterns::Prune,
+    Error
 };
 
+use std::time::Instant;
+
 use hashbrown::{HashSet, HashMap};
 
 pub use crate::patterns::Handler;
 
-#[derive(Debug,)]
+#[derive(Debug, Clone)]
 pub struct Rulebook(pub HashMap<u8, RuleEntry>);
 
-impl<'a, T> From<T> for Rulebook 
+impl Rulebook {
+    /// hash of the rules which stays the same across runs,
+    /// used to tell whether saved state belongs to these rules
+    pub fn fingerprint(&self) -> u64 {
+        const PRIME: u64 = 0x100000001b3;
+        let mut keys: Vec<&u8> = self.0.keys().collect();
+        keys.sort();
+
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for key in keys {
+            let entry = RuleCell::from(self.0[key].clone());
+            for byte in [*key, entry.len() as u8].iter().chain(entry.iter()) {
+                hash = (hash ^ *byte as u64).wrapping_mul(PRIME);
+            }
+        }
+        hash
+    }
+
+    /// replaces whatever `from` turned into before,
+    /// refusing to turn it into nothing, see `remove`
+    pub fn insert(&mut self, from: u8, to: RuleCell) -> Result<(), Error> {
+        if to.is_empty() {
+            return Err(Error::EmptyRule(from))
+        }
+        self.set(from, to);
+        Ok(())
+    }
+
+    /// drops the rule for `from`, handing back what it turned into
+    pub fn remove(&mut self, from: u8) -> Option<RuleCell> {
+        self.0.remove(&from).map(RuleCell::from)
+    }
+
+    /// replaces whatever `from` turned into before,
+    /// turning it into nothing drops the rule
+    pub(crate) fn set(&mut self, from: u8, to: RuleCell) {
+        match *to.as_slice() {
+            [] => { self.0.remove(&from); },
+            [byte] => { self.0.insert(from, RuleEntry::Single(byte)); },
+            _ => { self.0.insert(from, RuleEntry::Multi(to)); }
+        }
+    }
+
+    /// like `From`, but refuses rules turning a character into nothing
+    /// rather than leaving them out
+    pub fn try_from<T>(x: T) -> Result<Rulebook, Error>
+    where T: IntoIterator<Item=(u8, RuleCell)>
+    {
+        let mut rules = Rulebook(HashMap::new());
+        for (from, to) in x {
+            if to.is_empty() {
+                return Err(Error::EmptyRule(from))
+            }
+            rules.add(from, to, Merge::Union)?;
+        }
+        Ok(rules)
+    }
+
+    /// a single rule in the notation of `from:to`, where
+    /// everything after the colon is `to`, spaces included.
+    /// `\xHH` stands for the byte of hex value `HH`
+    pub fn parse_rule(x: &str) -> Result<(u8, RuleCell), Error> {
+        let (from, rest) = next_byte(x.as_bytes())
+            .ok_or_else(|| Error::InvalidRule(x.to_string()))?;
+        match rest {
+            [b':'] => Err(Error::EmptyRule(from)),
+            [b':', to @ ..] => {
+                let (mut to, mut bytes) = (to, RuleCell::new());
+                while let Some((byte, rest)) = next_byte(to) {
+                    bytes.push(byte);
+                    to = rest;
+                }
+                Ok((from, bytes))
+            },
+            _ => Err(Error::InvalidRule(x.to_string()))
+        }
+    }
+
+    /// `from:to` as `parse_rule` reads it back, escaping bytes
+    /// which aren't printable ascii or would read as something else
+    pub fn format_rule(from: u8, to: &[u8]) -> String {
+        let mut rule = match from {
+            b'#' => escape(from),
+            _ => to_text(&[from])
+        };
+        rule.push(':');
+        rule.push_str(&to_text(to));
+        rule
+    }
+
+    /// what `from` turns into, if anything
+    pub fn alternatives(&self, from: u8) -> Option<RuleCell> {
+        self.0.get(&from).map(|x| RuleCell::from(x.clone()))
+    }
+
+    /// adds `from:to` to the rules, combining it with
+    /// whatever `from` turned into before as `merge` says
+    pub fn add(&mut self, from: u8, to: RuleCell, merge: Merge) -> Result<(), Conflict> {
+        let existing = match self.alternatives(from) {
+            Some(existing) => existing,
+            None => {
+                self.set(from, dedup(to.iter()));
+                return Ok(())
+            }
+        };
+
+        match merge {
+            Merge::Union => self.set(from, dedup(existing.iter().chain(to.iter()))),
+            Merge::LastWins => self.set(from, dedup(to.iter())),
+            Merge::Strict if dedup(to.iter()) == existing => {},
+            Merge::Strict => return Err(Conflict { from, existing, new: to })
+        }
+        Ok(())
+    }
+
+    /// adds every rule of `other`, see `add`
+    pub fn merge(&mut self, other: &Rulebook, merge: Merge) -> Result<(), Conflict> {
+        let mut keys: Vec<&u8> = other.0.keys().collect();
+        keys.sort();
+
+        for from in keys {
+            self.add(*from, RuleCell::from(other.0[from].clone()), merge)?;
+        }
+        Ok(())
+    }
+
+    /// every alternative of either
+    pub fn union(&self, other: &Rulebook) -> Rulebook {
+        self.merged(other, Merge::Union)
+    }
+
+    /// these rules, with those `other` has for the same characters instead
+    pub fn override_with(&self, other: &Rulebook) -> Rulebook {
+        self.merged(other, Merge::LastWins)
+    }
+
+    /// alternatives `other` doesn't have, dropping characters left without any
+    pub fn subtract(&self, other: &Rulebook) -> Rulebook {
+        self.retain(|from, to| !other.alternatives(from).is_some_and(|x| x.contains(&to)))
+    }
+
+    /// alternatives both have
+    pub fn intersect(&self, other: &Rulebook) -> Rulebook {
+        self.retain(|from, to| other.alternatives(from).is_some_and(|x| x.contains(&to)))
+    }
+
+    fn merged(&self, other: &Rulebook, merge: Merge) -> Rulebook {
+        let mut rules = self.clone();
+        rules.merge(other, merge).expect("only strict merges conflict");
+        rules
+    }
+
+    fn retain<F>(&self, keep: F) -> Rulebook
+    where F: Fn(u8, u8) -> bool
+    {
+        let mut rules = Rulebook(HashMap::new());
+        for from in self.0.keys() {
+            let to: RuleCell = self.alternatives(*from)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|to| keep(*from, *to))
+                .collect();
+            if !to.is_empty() {
+                rules.set(*from, to);
+            }
+        }
+        rules
+    }
+
+    /// reads a rule file, like those `learn::Learner` writes
+    pub fn load<P>(path: P) -> Result<Self, Error>
+    where P: AsRef<std::path::Path>
+    {
+        std::fs::read_to_string(path)?.parse()
+    }
+}
+
+/// Rules for the same character are combined like `Merge::Union`,
+/// those turning a character into nothing are left out
+impl<T> From<T> for Rulebook 
 where T: IntoIterator<Item=(u8, RuleCell)>
 {
     fn from(x: T) -> Rulebook {
-        let mut map = HashMap::new();
+        let mut rules = Rulebook(HashMap::new());
         for (k, r) in x.into_iter() {
-            let rule = match r.len() {
-                1 => RuleEntry::Single(*r.get(0).unwrap()),
-                i if i > 1 => RuleEntry::Multi(RuleCell::from(r)),
-                _ => panic!("lolwhut")
-            };
-            map.insert(k, rule);
+            rules.add(k, r, Merge::Union).expect("unions never conflict");
+        }
+        rules
+    }
+}
+
+/// Written as a map of each character to what it turns into,
+/// `{ "a" = "4@", "s" = "5$" }` in toml
+/// every byte of a rule is an alternative of its own, so only
+/// ascii bytes stand for a character once written as text
+impl serde::Serialize for Rulebook {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        let text = |bytes: &[u8]| match bytes.is_ascii() {
+            true => Ok(bytes.iter().map(|x| *x as char).collect::<String>()),
+            false => Err(S::Error::custom(format!("Expected rules of ascii characters, got the bytes {:?}", bytes)))
+        };
+
+        let rules: std::collections::BTreeMap<String, String> = self.0.iter()
+            .map(|(from, to)| Ok((text(&[*from])?, text(&RuleCell::from(to.clone()))?)))
+            .collect::<Result<_, S::Error>>()?;
+        serializer.collect_map(rules)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Rulebook {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let rules: std::collections::BTreeMap<String, String> = serde::Deserialize::deserialize(deserializer)?;
+        let mut rulebook = Rulebook(HashMap::new());
+        for (from, to) in rules {
+            match from.as_bytes() {
+                [from] => rulebook.insert(*from, RuleCell::from(to.as_bytes())).map_err(D::Error::custom)?,
+                _ => return Err(D::Error::custom(format!("Expected rule as a character and what it turns into, got '{}:{}'", from, to)))
+            }
+        }
+        Ok(rulebook)
+    }
+}
+
+/// How to combine rules for a character which already has some
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Merge {
+    /// keep the alternatives of both, those already there first
+    #[default]
+    Union,
+    /// replace those already there
+    #[serde(alias = "override")]
+    LastWins,
+    /// refuse rules which differ from those already there
+    Strict,
+}
+
+impl std::str::FromStr for Merge {
+    type Err = String;
+
+    fn from_str(x: &str) -> Result<Self, String> {
+        Ok(match x.trim().to_ascii_lowercase().as_str() {
+            "union" => Merge::Union,
+            "override" | "last-wins" => Merge::LastWins,
+            "strict" => Merge::Strict,
+            _ => return Err(format!("Expected union, override or strict, got '{}'", x))
+        })
+    }
+}
+
+/// Differing rules for the same character under `Merge::Strict`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub from: u8,
+    pub existing: RuleCell,
+    pub new: RuleCell,
+}
+
+impl std::error::Error for Conflict {}
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rule = |to: &RuleCell| format!("{}:{}", self.from as char, String::from_utf8_lossy(to));
+        write!(f, "Conflicting rules {} and {}", rule(&self.existing), rule(&self.new))
+    }
+}
+
+/// the first byte of a rule, unescaped, and what follows it
+fn next_byte(x: &[u8]) -> Option<(u8, &[u8])> {
+    let hex = x.get(2..4).and_then(|x| std::str::from_utf8(x).ok());
+    match (x, hex) {
+        ([b'\\', b'x', ..], Some(hex)) if hex.bytes().all(|x| x.is_ascii_hexdigit()) => {
+            Some((u8::from_str_radix(hex, 16).ok()?, &x[4..]))
+        },
+        ([byte, rest @ ..], _) => Some((*byte, rest)),
+        ([], _) => None
+    }
+}
+
+fn escape(byte: u8) -> String {
+    format!("\\x{:02x}", byte)
+}
+
+fn to_text(bytes: &[u8]) -> String {
+    bytes.iter()
+        .map(|x| match x {
+            b'\\' => escape(*x),
+            b' '..=b'~' => (*x as char).to_string(),
+            _ => escape(*x)
+        })
+        .collect()
+}
+
+/// `bytes` in order, each only once
+fn dedup<'a, I>(bytes: I) -> RuleCell
+where I: Iterator<Item=&'a u8>
+{
+    let mut found = RuleCell::new();
+    for byte in bytes {
+        if !found.contains(byte) {
+            found.push(*byte);
         }
-        Rulebook(map)
+    }
+    found
+}
+
+/// One rule per line in the notation of `from:to`, where everything
+/// after the colon is `to`, spaces included, and `\xHH` is the byte of hex value `HH`.
+/// Lines starting with `#` are comments,
+/// rules for the same character are combined like `Merge::Union`
+impl std::str::FromStr for Rulebook {
+    type Err = Error;
+
+    fn from_str(x: &str) -> Result<Self, Error> {
+        Rulebook::try_from(
+            x.lines()
+                .map(|x| x.strip_suffix('\r').unwrap_or(x))
+                .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+                .map(Rulebook::parse_rule)
+                .collect::<Result<Vec<_>, Error>>()?
+        )
     }
 }
 
@@ -34,6 +355,34 @@ pub struct Generator<T> {
     rules: Rulebook,
     gen_ctr: usize,
     scheduler: T,
+    emitted: bool,
+    /// candidates of the current generation already emitted
+    offset: usize,
+    /// candidates arranged for emitting, or those
+    /// a resumed run has yet to emit
+    backlog: Vec<Cell>,
+    order: Order,
+    /// hand out what a buffered order is holding back
+    release: bool,
+    /// a buffered order has handed out everything
+    released: bool,
+    cancel: CancelToken,
+    pruner: Option<Arc<Pruner>>,
+    closure: Closure,
+    /// what `Closure::SingleHop` allows, once a generation has run
+    hops: Option<Arc<Hops>>,
+    /// every one of them has to keep a candidate for it to be emitted
+    pub(crate) filters: Vec<Arc<dyn Filter>>,
+    /// kept apart from `filters`, so setting another policy replaces it
+    policy_filter: Option<Arc<Policy>>,
+    model: Option<Arc<Model>>,
+    seeds: Vec<Cell>,
+    keyspaces: Option<Vec<Keyspace>>,
+    emitted_ctr: u128,
+    resumed_ctr: u128,
+    started: Instant,
+    /// one per generation this run
+    stats: Vec<GenerationStats>,
 }
 
 impl<T> Generator<T>
@@ -47,6 +396,25 @@ impl<T> Generator<T>
             rules: rules.into(),
             gen_ctr: 0,
             scheduler,
+            emitted: false,
+            offset: 0,
+            backlog: Vec::new(),
+            order: Order::default(),
+            release: false,
+            released: false,
+            cancel: CancelToken::new(),
+            pruner: None,
+            closure: Closure::default(),
+            hops: None,
+            filters: Vec::new(),
+            policy_filter: None,
+            model: None,
+            seeds: Vec::new(),
+            keyspaces: None,
+            emitted_ctr: 0,
+            resumed_ctr: 0,
+            started: Instant::now(),
+            stats: Vec::new(),
         //  buf
         }
     }
@@ -57,19 +425,367 @@ impl<T> Generator<T>
     pub fn map(&self) -> &Rulebook {
         &self.rules
     }
+
+    pub fn scheduler(&self) -> &T {
+        &self.scheduler
+    }
     
-    pub fn new_generation<H>(&mut self)
+    /// permutates the candidates new to the last generation,
+    /// failing with whatever the scheduler's storage met since
+    /// the last call, candidates it couldn't hold are lost
+    pub fn new_generation<H>(&mut self) -> Result<(), Error>
     where
         H: Handler,
-        T: Scheduler
+        T: Scheduler + Length
+    {
+        if self.keyspaces.is_none() && !self.seeds.is_empty() {
+            self.keyspaces = Some(self.seeds.iter()
+                .map(|seed| self.keyspace::<H>(seed))
+                .collect());
+        }
+        if self.closure == Closure::SingleHop && self.hops.is_none() {
+            let mut hops = Hops::new::<H>(&self.rules);
+            self.seeds.iter().for_each(|seed| hops.add(seed));
+            self.hops = Some(Arc::new(hops));
+            self.prune();
+        }
+
+        self.scheduler.settle();
+        let (before, started) = (self.scheduler.length(), Instant::now());
+        let produced = self.scheduler.new_generation::<H>(&self.rules);
+        let added = self.scheduler.length().saturating_sub(before);
+        self.gen_ctr += 1;
+        self.stats.push(GenerationStats {
+            generation: self.gen_ctr,
+            produced,
+            added,
+            duplicates: produced.saturating_sub(added),
+            elapsed: started.elapsed(),
+        });
+
+        self.emitted = false;
+        self.offset = 0;
+        self.backlog.clear();
+
+        match self.scheduler.take_error() {
+            Some(e) => Err(e),
+            None => Ok(())
+        }
+    }
+
+    /// the first error the scheduler met since this or `new_generation`
+    /// was last called, for checking on emits after the last generation
+    pub fn take_error(&mut self) -> Option<Error>
+    where T: Scheduler
+    {
+        self.scheduler.take_error()
+    }
+
+    /// enumerates `shard`'s slice of the seeds' keyspaces straight away,
+    /// instead of going through generations. Running every shard
+    /// yields each candidate of every seed exactly once
+    pub fn shard<H>(&mut self, shard: Shard) -> Cells
+    where H: Handler
+    {
+        let keyspaces: Vec<Keyspace> = self.seeds.iter()
+            .map(|seed| self.keyspace::<H>(seed))
+            .collect();
+        let sizes: Vec<u128> = keyspaces.iter().map(Keyspace::size).collect();
+        let ranges = shard.ranges(&sizes);
+
+        let items = ranges.into_iter().flat_map(move |(seed, range)| {
+            let keyspace = keyspaces[seed].clone();
+            range.map_while(move |idx| keyspace.get(idx))
+        });
+        self.allowed(Box::new(items))
+    }
+
+    fn keyspace<H>(&self, seed: &Cell) -> Keyspace
+    where H: Handler
+    {
+        match self.closure {
+            Closure::SingleHop => Keyspace::single_hop::<H>(seed, &self.rules),
+            _ => Keyspace::new::<H>(seed, &self.rules)
+        }
+    }
+
+    /// how far rules carry the seeds' characters, set it before the first generation.
+    /// `Closure::Transitive` replaces the rules with their `Rulebook::closure`
+    pub fn set_closure(&mut self, closure: Closure) {
+        if closure == Closure::Transitive {
+            self.rules = self.rules.closure();
+        }
+        self.closure = closure;
+    }
+
+    pub fn closure(&self) -> Closure {
+        self.closure
+    }
+
+    /// order `emit` hands candidates out in, set it before emitting any
+    pub fn set_order(&mut self, order: Order) {
+        self.order = order;
+    }
+
+    pub fn order(&self) -> Order {
+        self.order
+    }
+
+    /// for stopping early, the next `emit` hands out
+    /// whatever a buffered order was still holding back
+    pub fn release(&mut self) {
+        if self.order.is_buffered() && !self.released {
+            self.release = true;
+            self.emitted = false;
+        }
+    }
+
+    /// stops the generation in progress, and any after it, once `token`
+    /// is cancelled. hand the same token to whatever decides when to stop
+    pub fn set_cancel(&mut self, token: CancelToken)
+    where T: Scheduler
+    {
+        self.scheduler.set_cancel(token.clone());
+        self.cancel = token;
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// only emits candidates satisfying `policy`, and stops
+    /// permutating those which never could. replaces any policy set before
+    pub fn set_policy(&mut self, policy: Policy)
+    where T: Scheduler
+    {
+        self.policy_filter = Some(Arc::new(policy.clone()));
+        self.pruner = Some(Arc::new(Pruner::new(policy, &self.rules)));
+        self.prune();
+    }
+
+    /// hands the scheduler everything giving up on candidates
+    fn prune(&mut self)
+    where T: Scheduler
+    {
+        let mut pruners: Vec<Arc<dyn Prune>> = Vec::new();
+        if let Some(pruner) = &self.pruner {
+            pruners.push(pruner.clone());
+        }
+        if let Some(hops) = &self.hops {
+            pruners.push(hops.clone());
+        }
+
+        match pruners.len() {
+            0 => {},
+            1 => self.scheduler.set_pruner(pruners.remove(0)),
+            _ => self.scheduler.set_pruner(Arc::new(pruners))
+        }
+    }
+
+    pub fn policy(&self) -> Option<&Policy> {
+        self.pruner.as_deref().map(Pruner::policy)
+    }
+
+    /// only emits candidates `filter` keeps, on top of any added before
+    pub fn add_filter<F>(&mut self, filter: F)
+    where F: Filter + 'static
+    {
+        self.filters.push(Arc::new(filter));
+    }
+
+    /// scores candidates for `Order::Likelihood`
+    pub fn set_model(&mut self, model: Arc<Model>) {
+        self.model = Some(model);
+    }
+
+    /// drops whichever of `items` a filter doesn't keep
+    fn allowed(&self, items: Cells) -> Cells {
+        let mut filters = self.filters.clone();
+        if let Some(policy) = &self.policy_filter {
+            filters.push(policy.clone());
+        }
+        if let Some(hops) = &self.hops {
+            filters.push(hops.clone());
+        }
+        if filters.is_empty() {
+            return items
+        }
+
+        Box::new(items.filter(move |x| filters.iter().all(|filter| filter.keep(x))))
+    }
+
+    /// hands every candidate found by the last generation to `sink`,
+    /// or the seeds if there hasn't been one yet.
+    /// Candidates are only handed out once, returns how many were.
+    pub fn emit<F>(&mut self, sink: F) -> usize
+    where
+        T: Scheduler + Snapshot,
+        F: FnMut(Cell)
+    {
+        self.emit_at_most(usize::MAX, sink)
+    }
+
+    /// like `emit`, but stops after `max` candidates,
+    /// the next call carries on from there
+    pub fn emit_at_most<F>(&mut self, max: usize, mut sink: F) -> usize
+    where
+        T: Scheduler + Snapshot,
+        F: FnMut(Cell)
+    {
+        let mut items = self.unemitted();
+        let mut count = 0;
+        while count < max {
+            match items.next() {
+                Some(item) => sink(item),
+                None => break
+            }
+            count += 1;
+        }
+
+        if count < max || items.next().is_none() {
+            self.emitted = true;
+            self.offset = 0;
+            self.backlog.clear();
+        }
+        else {
+            self.offset += count;
+        }
+
+        self.emitted_ctr += count as u128;
+        count
+    }
+
+    /// candidates `emit` has yet to hand out
+    pub fn unemitted(&mut self) -> Cells
+    where T: Scheduler + Snapshot
+    {
+        self.arrange();
+
+        let items: Cells = if self.emitted {
+            Box::new(std::iter::empty())
+        }
+        else if !self.backlog.is_empty() {
+            Box::new(self.backlog.clone().into_iter())
+        }
+        else {
+            self.scheduler.fresh()
+        };
+        Box::new(self.allowed(items).skip(self.offset))
+    }
+
+    /// sorts the candidates about to be emitted into `backlog`,
+    /// unless they're unordered or already arranged
+    fn arrange(&mut self)
+    where T: Scheduler + Snapshot
+    {
+        if self.emitted || self.offset > 0 || !self.backlog.is_empty() {
+            return
+        }
+
+        let mut items: Vec<Cell> = match self.order {
+            Order::Unordered => return,
+            Order::Substitutions => self.scheduler.fresh().collect(),
+            Order::Lexicographic | Order::Index | Order::Likelihood => {
+                // held back until a generation finds nothing new
+                if self.released || !self.release && self.scheduler.fresh().next().is_some() {
+                    self.emitted = true;
+                    return
+                }
+                self.released = true;
+                self.scheduler.snapshot().collect()
+            }
+        };
+
+        match (self.order, &self.keyspaces, &self.model) {
+            (Order::Index, Some(keyspaces), _) => items.sort_by_cached_key(|item| {
+                locate(keyspaces, item).unwrap_or((usize::MAX, 0))
+            }),
+            (Order::Likelihood, _, Some(model)) => {
+                let mut scored: Vec<(f64, Cell)> = items.into_iter()
+                    .map(|item| (model.score(&item), item))
+                    .collect();
+                scored.sort_unstable_by(|x, y| y.0.total_cmp(&x.0).then_with(|| x.1.cmp(&y.1)));
+                items = scored.into_iter().map(|(_, item)| item).collect();
+            },
+            _ => items.sort_unstable()
+        }
+        self.backlog = items;
+    }
+
+    /// the seed `item` was found from
+    pub fn origin(&self, item: &[u8]) -> Option<&Cell> {
+        match &self.keyspaces {
+            Some(keyspaces) => locate(keyspaces, item).map(|(seed, _)| &self.seeds[seed]),
+            None => self.seeds.iter().find(|seed| seed.as_slice() == item)
+        }
+    }
+
+    /// candidates emitted so far against the keyspace of the seeds,
+    /// which is only known once a generation has run
+    pub fn progress(&self) -> Progress {
+        Progress {
+            emitted: self.emitted_ctr,
+            resumed: self.resumed_ctr,
+            keyspace: self.keyspaces.as_ref()
+                .map(|x| x.iter().map(Keyspace::size).fold(0, u128::saturating_add)),
+            elapsed: self.started.elapsed(),
+        }
+    }
+
+    /// every candidate found so far, for saving a run's progress
+    pub fn snapshot(&mut self) -> Cells
+    where T: Scheduler + Snapshot
+    {
+        self.scheduler.settle();
+        self.scheduler.snapshot()
+    }
+
+    /// loads candidates an earlier run found from `seeds`, the next
+    /// generation permutates them. `emit` only hands out those among
+    /// them which are `pending`, the rest were already emitted.
+    ///
+    /// every one of `items` is permutated again, not only the last
+    /// generation's: a cancelled generation takes the candidates it
+    /// never got to without permutating them, resuming is what reaches them
+    pub fn resume<S, P, I>(&mut self, seeds: S, pending: P, items: I)
+    where
+        T: Scheduler,
+        S: IntoIterator<Item=Cell>,
+        P: IntoIterator<Item=Cell>,
+        I: IntoIterator<Item=Cell>
     {
-        self.scheduler.new_generation::<H>(&self.rules)
+        self.seeds.extend(seeds);
+        self.keyspaces = None;
+        self.hops = None;
+        self.backlog = pending.into_iter().collect();
+        self.offset = 0;
+
+        let mut count: u128 = 0;
+        for item in items {
+            self.scheduler.push(item);
+            count += 1;
+        }
+        let done = count.saturating_sub(self.backlog.len() as u128);
+        self.resumed_ctr += done;
+        self.emitted_ctr += done;
+        self.emitted = self.backlog.is_empty();
     }
 
+    /// generations run so far, see `set_generation`
     pub fn generation(&self) -> usize {
         self.gen_ctr
     }
 
+    /// counts on from `generation`, for a resumed run
+    pub fn set_generation(&mut self, generation: usize) {
+        self.gen_ctr = generation;
+    }
+
+    /// what each generation of this run did, oldest first
+    pub fn stats(&self) -> &[GenerationStats] {
+        &self.stats
+    }
+
     pub fn length(&self) -> usize 
     where T: Scheduler + Length
     {
@@ -82,21 +798,51 @@ impl<T> Generator<T>
         self.scheduler.clean_state()
     }
 
+    /// blocks until the scheduler has no outstanding work,
+    /// `new_generation` already does this before returning
+    pub fn wait_idle(&self)
+    where T: Scheduler
+    {
+        self.scheduler.wait_idle()
+    }
+
     pub fn into_set(self) -> HashSet<Cell> 
     where T: Scheduler + Into<HashSet<Cell>>
     {
         self.scheduler.into()
     }
 
+    /// hands out every candidate found, without
+    /// gathering them into a single set first
+    pub fn drain(&mut self) -> Cells
+    where T: Scheduler + Drain
+    {
+        self.scheduler.drain()
+    }
+
     pub fn seed<I>(&mut self, item: I)
     where 
         T: Scheduler,
         I: Into<Cell>
     {
-        self.scheduler.push(item.into());
+        let item = item.into();
+        self.seeds.push(item.clone());
+        self.keyspaces = None;
+        if let Some(hops) = &mut self.hops {
+            Arc::make_mut(hops).add(&item);
+            self.prune();
+        }
+        self.scheduler.push(item);
     }
 }
 
+/// which seed's keyspace holds `item`, and where
+fn locate(keyspaces: &[Keyspace], item: &[u8]) -> Option<(usize, u128)> {
+    keyspaces.iter()
+        .enumerate()
+        .find_map(|(seed, keyspace)| keyspace.index_of(item).map(|idx| (seed, idx)))
+}
+
 // pub trait PreEmptiveAlloc {
 //     fn init_capacity(size: usize) -> Self;
 // }
@@ -111,9 +857,156 @@ impl Length for HashSet<Cell> {
     }
 }
 
+/// Takes every candidate out of a scheduler
+pub trait Drain {
+    fn drain(&mut self) -> Cells;
+}
+
+/// Copies candidates out of a scheduler, leaving them in place
+pub trait Snapshot {
+    /// candidates new to the last generation,
+    /// or the seeds before the first one
+    fn fresh(&mut self) -> Cells;
+
+    /// every candidate held
+    fn snapshot(&mut self) -> Cells;
+}
+
 use std::sync::{Arc, Mutex};
 impl Length for Arc<Mutex<HashSet<Cell>>> {
     fn length(&self) -> usize {
         self.lock().unwrap().len()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules(x: &str) -> Rulebook {
+        x.parse().unwrap()
+    }
+
+    fn to(rules: &Rulebook, from: u8) -> Option<Vec<u8>> {
+        rules.alternatives(from).map(|x| x.to_vec())
+    }
+
+    #[test]
+    fn behavior_rulebook() {
+        let both: Rulebook = vec![
+            (b'a', RuleCell::from(&b"4"[..])),
+            (b'a', RuleCell::from(&b"@4"[..])),
+        ].into();
+        assert_eq!(to(&both, b'a'), Some(b"4@".to_vec()));
+        assert_eq!(rules("a:4\na:@").fingerprint(), both.fingerprint());
+
+        let mut strict = rules("a:4@\ns:5");
+        assert!(strict.add(b'a', RuleCell::from(&b"4@"[..]), Merge::Strict).is_ok());
+        let conflict = strict.add(b'a', RuleCell::from(&b"@"[..]), Merge::Strict).unwrap_err();
+        assert_eq!(conflict.to_string(), "Conflicting rules a:4@ and a:@");
+        assert!(strict.merge(&rules("o:0\ns:$"), Merge::Strict).is_err());
+
+        let preset = rules("a:4@\ns:5$\ne:3");
+        let tweaks = rules("a:^\ns:5\no:0");
+
+        let union = preset.union(&tweaks);
+        assert_eq!(to(&union, b'a'), Some(b"4@^".to_vec()));
+        assert_eq!(to(&union, b'o'), Some(b"0".to_vec()));
+
+        let overridden = preset.override_with(&tweaks);
+        assert_eq!(to(&overridden, b'a'), Some(b"^".to_vec()));
+        assert_eq!(to(&overridden, b'e'), Some(b"3".to_vec()));
+
+        let subtracted = preset.subtract(&rules("s:5$\ne:4\na:@"));
+        assert_eq!(to(&subtracted, b'a'), Some(b"4".to_vec()));
+        assert_eq!(to(&subtracted, b's'), None);
+        assert_eq!(to(&subtracted, b'e'), Some(b"3".to_vec()));
+
+        let both = preset.intersect(&tweaks);
+        assert_eq!(both.0.len(), 1);
+        assert_eq!(to(&both, b's'), Some(b"5".to_vec()));
+    }
+
+    #[test]
+    fn behavior_errors() {
+        let empty = vec![(b'a', RuleCell::from(&b"4"[..])), (b's', RuleCell::new())];
+        assert!(matches!(Rulebook::try_from(empty.clone()), Err(Error::EmptyRule(b's'))));
+        let rules = Rulebook::from(empty);
+        assert_eq!((to(&rules, b'a'), to(&rules, b's')), (Some(b"4".to_vec()), None));
+
+        assert!(matches!("a:4\ns:".parse::<Rulebook>(), Err(Error::EmptyRule(b's'))));
+        assert!(matches!("a:4\né:e".parse::<Rulebook>(), Err(Error::InvalidRule(x)) if x == "é:e"));
+        assert_eq!(Rulebook::parse_rule(":: ").unwrap(), (b':', RuleCell::from(&b" "[..])));
+        assert_eq!(Rulebook::parse_rule("a4").unwrap_err().to_string(), "Expected rule as `from:to`, got 'a4'");
+        assert!(matches!(
+            Rulebook::try_from(vec![(b'a', RuleCell::from(&b"4"[..]))]).unwrap().add(b'a', RuleCell::from(&b"@"[..]), Merge::Strict).map_err(Error::from),
+            Err(Error::Conflict(_))
+        ));
+
+        let mut rules = rules;
+        assert!(matches!(rules.insert(b'a', RuleCell::new()), Err(Error::EmptyRule(b'a'))));
+        assert_eq!(rules.remove(b'a'), Some(RuleCell::from(&b"4"[..])));
+        assert!(rules.0.is_empty());
+
+        assert!(matches!(crate::storage::Bloom::new(usize::MAX, 1e-300), Err(Error::CapacityOverflow)));
+        assert!(matches!(Rulebook::load("/nonexistent/rules"), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn generation_stats() {
+        use crate::{patterns::ModulusPattern, scheduler::{SingleThread, WorkStealing}};
+
+        fn run<T>(mut gen: Generator<T>) -> Vec<(usize, usize, usize)>
+        where T: Scheduler + Length
+        {
+            gen.seed(&b"password"[..]);
+            while gen.stats().last().is_none_or(|x| x.added > 0) {
+                gen.new_generation::<ModulusPattern>().unwrap();
+            }
+            assert_eq!(gen.generation(), gen.stats().len());
+            assert_eq!(gen.stats().iter().map(|x| x.added).sum::<usize>() + 1, gen.length());
+            gen.stats().iter().map(|x| (x.produced, x.added, x.duplicates)).collect()
+        }
+
+        let found = run(Generator::new(rules("a:4@\ns:5$\no:0"), SingleThread::new()));
+        assert_eq!(found, vec![(7, 7, 0), (36, 18, 18), (60, 20, 40), (32, 8, 24), (0, 0, 0)]);
+        assert_eq!(run(Generator::new(rules("a:4@\ns:5$\no:0"), WorkStealing::new(2).unwrap())), found);
+
+        let mut resumed = Generator::new(rules("a:4"), SingleThread::new());
+        resumed.set_generation(3);
+        resumed.seed(&b"a"[..]);
+        resumed.new_generation::<ModulusPattern>().unwrap();
+        assert_eq!((resumed.generation(), resumed.stats()[0].generation), (4, 4));
+    }
+
+    #[test]
+    fn behavior_serde() {
+        use crate::{closure::Closure, keyspace::Shard, policy::{Class, Policy}};
+
+        let preset = rules("a:4@\ns:5$");
+        let written = serde_json::to_string(&preset).unwrap();
+        assert_eq!(written, r#"{"a":"4@","s":"5$"}"#);
+        let read: Rulebook = serde_json::from_str(&written).unwrap();
+        assert_eq!(read.fingerprint(), preset.fingerprint());
+        assert!(serde_json::from_str::<Rulebook>(r#"{"ab":"4"}"#).is_err());
+        assert!(serde_json::from_str::<Rulebook>(r#"{"a":""}"#).is_err());
+        assert!(serde_json::to_string(&Rulebook::from(vec![(0xe9, RuleCell::from(&b"e"[..]))])).is_err());
+        assert!(serde_json::to_string(&Rulebook::from(vec![(b'e', RuleCell::from(&[0xe9][..]))])).is_err());
+
+        let policy: Policy = serde_json::from_str(r#"{"min_len":8,"require":["digit","symbol"]}"#).unwrap();
+        assert_eq!((policy.min_len, policy.max_len), (8, usize::MAX));
+        assert_eq!(policy.require, vec![Class::Digit, Class::Symbol]);
+        assert_eq!(serde_json::to_string(&policy).unwrap(), r#"{"min_len":8,"require":["digit","symbol"]}"#);
+
+        assert_eq!(serde_json::to_string(&(Order::Likelihood, Closure::SingleHop, Merge::LastWins)).unwrap(), r#"["likelihood","single-hop","last-wins"]"#);
+        assert_eq!(serde_json::from_str::<Merge>(r#""override""#).unwrap(), Merge::LastWins);
+
+        let shard: Shard = serde_json::from_str(r#""2/4""#).unwrap();
+        assert_eq!((shard.index(), shard.count()), (2, 4));
+        assert!(serde_json::from_str::<Shard>(r#""5/4""#).is_err());
+
+        let include: crate::Include = serde_json::from_str(r#""[0-9]$""#).unwrap();
+        assert!(crate::Filter::keep(&include, b"pass1"));
+        assert_eq!(serde_json::to_string(&include).unwrap(), r#""[0-9]$""#);
+    }
+}
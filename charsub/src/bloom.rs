@@ -0,0 +1,334 @@
+use crate::{
+    Cell, Error,
+    storage::{Storage, Cells}
+};
+
+use std::hash::BuildHasher;
+
+use hashbrown::hash_map::DefaultHashBuilder;
+
+/// Storage deduplicating through a Bloom filter instead of an exact set.
+/// Candidates are packed back to back into one buffer, so each costs
+/// little more than its own bytes.
+///
+/// A false positive drops a candidate that was actually new,
+/// which happens at roughly the configured error rate
+/// for as long as no more than `capacity` candidates are held.
+///
+/// Only the latest generation is held, unless `retaining` keeps every
+/// candidate for `snapshot` and `drain`, which refuse to work without it.
+#[derive(Debug)]
+pub struct Bloom {
+    filter: Filter,
+    all: Option<Arena>,
+    pending: Arena,
+    fresh: Arena,
+    settled: usize,
+    dropped: usize,
+    error: Option<Error>
+}
+
+impl Bloom {
+    /// sizes the filter for `capacity` candidates at `error` false positive rate,
+    /// refusing a filter too large to address or an `error` which isn't between 0 and 1
+    pub fn new(capacity: usize, error: f64) -> Result<Self, Error> {
+        Ok(Self {
+            filter: Filter::new(capacity, error)?,
+            all: None,
+            pending: Arena::default(),
+            fresh: Arena::default(),
+            settled: 0,
+            dropped: 0,
+            error: None
+        })
+    }
+
+    /// keeps every candidate found, as a checkpoint or
+    /// an ordered run needs, at the cost of their bytes
+    pub fn retaining(mut self) -> Self {
+        self.all.get_or_insert_with(Arena::default);
+        self
+    }
+
+    /// every candidate found, if they're kept,
+    /// otherwise an empty iterator and an error for `take_error`
+    fn retained(&mut self) -> Option<&mut Arena> {
+        if self.all.is_none() && self.error.is_none() {
+            self.error = Some(Error::InvalidConfig("bloom storage only keeps every candidate if it's retaining".into()));
+        }
+        self.all.as_mut()
+    }
+
+    /// candidates turned away as duplicates, this includes
+    /// false positives, of which there are about
+    /// `dropped() * false_positive_rate()`
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// chance the next new candidate is mistaken for a duplicate
+    pub fn false_positive_rate(&self) -> f64 {
+        self.filter.false_positive_rate()
+    }
+
+    /// bytes held by the filter and the candidates
+    pub fn memory(&self) -> usize {
+        let all = self.all.as_ref().map_or(0, |x| x.buf.len());
+        self.filter.bits.len() * 8 + all + self.pending.buf.len() + self.fresh.buf.len()
+    }
+}
+
+impl Storage for Bloom {
+    fn insert(&mut self, item: Cell) {
+        if self.filter.insert(&item) {
+            self.pending.push(&item);
+        }
+        else {
+            self.dropped += 1;
+        }
+    }
+
+    fn commit(&mut self) -> usize {
+        let new = self.pending.len;
+        if let Some(all) = &mut self.all {
+            all.extend(&self.pending);
+        }
+        self.fresh.extend(&self.pending);
+        self.pending = Arena::default();
+        self.settled += new;
+        new
+    }
+
+    fn take_fresh(&mut self) -> Cells {
+        Box::new(std::mem::take(&mut self.fresh).into_iter())
+    }
+
+    fn fresh(&mut self) -> Cells {
+        Box::new(self.fresh.clone().into_iter())
+    }
+
+    fn snapshot(&mut self) -> Cells {
+        self.commit();
+        let all = self.retained().cloned().unwrap_or_default();
+        Box::new(all.into_iter())
+    }
+
+    fn len(&self) -> usize {
+        self.settled
+    }
+
+    fn drain(&mut self) -> Cells {
+        self.commit();
+        self.fresh = Arena::default();
+        self.filter.clear();
+        self.settled = 0;
+        let all = self.retained().map(std::mem::take).unwrap_or_default();
+        Box::new(all.into_iter())
+    }
+
+    fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+}
+
+#[derive(Debug)]
+struct Filter {
+    hasher: DefaultHashBuilder,
+    bits: Vec<u64>,
+    hashes: u64,
+    set: u64
+}
+
+impl Filter {
+    fn new(capacity: usize, error: f64) -> Result<Self, Error> {
+        if !(error > 0.0 && error < 1.0) {
+            return Err(Error::InvalidConfig(format!("bloom false positive rate has to be between 0 and 1, got {}", error)))
+        }
+        let capacity = capacity.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+
+        let bits = (-capacity * error.ln() / (ln2 * ln2)).ceil().max(64.0);
+        let hashes = (bits / capacity * ln2).round().max(1.0);
+        if bits >= usize::MAX as f64 || bits / 8.0 > isize::MAX as f64 {
+            return Err(Error::CapacityOverflow)
+        }
+
+        Ok(Self {
+            hasher: DefaultHashBuilder::default(),
+            bits: vec![0; (bits as usize).div_ceil(64)],
+            hashes: hashes as u64,
+            set: 0
+        })
+    }
+
+    /// returns `true` if `item` wasn't already (seemingly) present
+    fn insert(&mut self, item: &Cell) -> bool {
+        let m = self.bits.len() as u64 * 64;
+        let hash = self.hasher.hash_one(item);
+        let step = hash.rotate_left(32) | 1;
+
+        let mut new = false;
+        for i in 0..self.hashes {
+            let bit = hash.wrapping_add(i.wrapping_mul(step)) % m;
+            let (word, mask) = ((bit / 64) as usize, 1 << (bit % 64));
+
+            if self.bits[word] & mask == 0 {
+                self.bits[word] |= mask;
+                self.set += 1;
+                new = true;
+            }
+        }
+        new
+    }
+
+    fn false_positive_rate(&self) -> f64 {
+        let fill = self.set as f64 / (self.bits.len() as f64 * 64.0);
+        fill.powi(self.hashes as i32)
+    }
+
+    fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+        self.set = 0;
+    }
+}
+
+/// Candidates packed back to back,
+/// each prefixed with its length as a varint
+#[derive(Debug, Default, Clone)]
+struct Arena {
+    buf: Vec<u8>,
+    len: usize
+}
+
+impl Arena {
+    fn push(&mut self, item: &[u8]) {
+        let mut size = item.len();
+        while size >= 0x80 {
+            self.buf.push(size as u8 | 0x80);
+            size >>= 7;
+        }
+        self.buf.push(size as u8);
+        self.buf.extend_from_slice(item);
+        self.len += 1;
+    }
+
+    fn extend(&mut self, other: &Arena) {
+        self.buf.extend_from_slice(&other.buf);
+        self.len += other.len;
+    }
+}
+
+impl IntoIterator for Arena {
+    type Item = Cell;
+    type IntoIter = ArenaIter;
+
+    fn into_iter(self) -> ArenaIter {
+        ArenaIter { buf: self.buf, pos: 0 }
+    }
+}
+
+struct ArenaIter {
+    buf: Vec<u8>,
+    pos: usize
+}
+
+impl Iterator for ArenaIter {
+    type Item = Cell;
+
+    fn next(&mut self) -> Option<Cell> {
+        if self.pos >= self.buf.len() {
+            return None
+        }
+
+        let (mut size, mut shift) = (0, 0);
+        loop {
+            let byte = self.buf[self.pos];
+            self.pos += 1;
+            size |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break
+            }
+        }
+
+        let item = Cell::from(&self.buf[self.pos..self.pos + size]);
+        self.pos += size;
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Length, RuleCell, Rulebook,
+        patterns::ModulusPattern,
+        scheduler::{Scheduler, SingleThread}
+    };
+    use hashbrown::HashSet;
+
+    #[test]
+    fn behavior_bloom() {
+        let rules: Rulebook = vec![
+            (b'o', RuleCell::from(&b"0()"[..])),
+            (b'l', RuleCell::from(&b"1|!"[..])),
+            (b'0', RuleCell::from(&b"oO"[..])),
+        ].into();
+
+        let mut memory = SingleThread::new();
+        let mut bloom = SingleThread::with_storage(Bloom::new(100_000, 1e-9).unwrap().retaining());
+        memory.push(Cell::from(&b"lollipop"[..]));
+        bloom.push(Cell::from(&b"lollipop"[..]));
+
+        for _ in 0..8 {
+            memory.new_generation::<ModulusPattern>(&rules);
+            bloom.new_generation::<ModulusPattern>(&rules);
+            assert_eq!(memory.length(), bloom.length());
+        }
+        assert!(bloom.storage().dropped() > 0);
+
+        let memory: HashSet<Cell> = memory.into();
+        let bloom: HashSet<Cell> = bloom.into();
+        assert_eq!(memory, bloom);
+    }
+
+    #[test]
+    fn arena_roundtrip() {
+        let mut arena = Arena::default();
+        let long = vec![b'x'; 300];
+        for item in [&b""[..], b"abc", &long] {
+            arena.push(item);
+        }
+
+        let items: Vec<Cell> = arena.into_iter().collect();
+        assert_eq!(items, vec![Cell::new(), Cell::from(&b"abc"[..]), Cell::from(&long[..])]);
+    }
+
+    #[test]
+    fn holds_one_generation() {
+        let mut bloom = Bloom::new(1000, 1e-6).unwrap();
+        for item in [&b"one"[..], b"two", b"one"] {
+            bloom.insert(Cell::from(item));
+        }
+        assert_eq!(bloom.len(), 0);
+        assert_eq!(bloom.commit(), 2);
+        assert_eq!(bloom.len(), 2);
+        assert_eq!(bloom.take_fresh().count(), 2);
+
+        bloom.insert(Cell::from(&b"three"[..]));
+        bloom.commit();
+        assert_eq!(bloom.len(), 3);
+        assert_eq!(bloom.memory(), bloom.filter.bits.len() * 8 + 6);
+
+        assert_eq!(bloom.snapshot().count(), 0);
+        assert!(matches!(bloom.take_error(), Some(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn rejects_error_rates() {
+        for error in [0.0, -0.1, 1.0, 1.5, f64::NAN] {
+            assert!(matches!(Bloom::new(10, error), Err(Error::InvalidConfig(_))));
+        }
+        assert!(Bloom::new(10, 0.5).is_ok());
+    }
+}
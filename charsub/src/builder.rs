@@ -0,0 +1,707 @@
+use crate::{
+    Cell, Error, Generator, Handler, Length, Drain, Snapshot,
+    Merge, Permutation, RuleCell, Rulebook, Scheduler,
+    cancel::CancelToken,
+    closure::Closure,
+    keyspace::Shard,
+    markov::Model,
+    order::Order,
+    patterns::{ConstPattern, Filter, ModulusPattern, Pattern, Prune},
+    policy::Policy,
+    scheduler::{MultithreadMutex, SingleThread, TokioMutex, WorkStealing},
+    storage::{Bloom, Cells, Spill}
+};
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, mpsc},
+    time::Duration
+};
+
+use hashbrown::HashSet;
+
+/// How candidates already found are told apart from new ones
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Dedup {
+    /// every candidate in memory
+    #[default]
+    Exact,
+    /// candidates beyond `budget` bytes are written to disk,
+    /// under `dir` or the system's temporary directory
+    Spill { budget: usize, dir: Option<PathBuf> },
+    /// a bloom filter for `capacity` candidates at `error` false positive rate,
+    /// `retain` keeps every candidate as ordered runs and checkpoints need,
+    /// see `storage::Bloom`
+    Bloom { capacity: usize, error: f64, retain: bool },
+}
+
+/// Each scheduler as one type, for choosing one at runtime
+#[derive(Debug)]
+pub enum Schedules {
+    Async(TokioMutex),
+    Pool(MultithreadMutex),
+    SingleThread(SingleThread),
+    Spill(SingleThread<Spill>),
+    Bloom(SingleThread<Bloom>),
+    Steal(WorkStealing),
+}
+
+impl Schedules {
+    /// a single thread deduplicating as `dedup` says,
+    /// or work stealing across `threads` deduplicating exactly
+    pub fn new(threads: usize, dedup: &Dedup) -> Result<Self, Error> {
+        Ok(match dedup {
+            Dedup::Exact if threads > 1 => Schedules::Steal(WorkStealing::new(threads)?),
+            Dedup::Exact => Schedules::SingleThread(SingleThread::new()),
+            Dedup::Spill { budget, dir: Some(dir) } => Schedules::Spill(SingleThread::with_storage(Spill::new_in(*budget, dir)?)),
+            Dedup::Spill { budget, dir: None } => Schedules::Spill(SingleThread::with_storage(Spill::new(*budget)?)),
+            Dedup::Bloom { capacity, error, retain } => {
+                let bloom = Bloom::new(*capacity, *error)?;
+                Schedules::Bloom(SingleThread::with_storage(if *retain { bloom.retaining() } else { bloom }))
+            }
+        })
+    }
+}
+
+impl Scheduler for Schedules {
+    fn push(&mut self, item: Cell) {
+        match self {
+            Schedules::Async(rt) => rt.push(item),
+            Schedules::Pool(pool) => pool.push(item),
+            Schedules::SingleThread(func) => func.push(item),
+            Schedules::Spill(func) => func.push(item),
+            Schedules::Bloom(func) => func.push(item),
+            Schedules::Steal(pool) => pool.push(item)
+        }
+    }
+
+    fn settle(&mut self) {
+        match self {
+            Schedules::Async(rt) => rt.settle(),
+            Schedules::Pool(pool) => pool.settle(),
+            Schedules::SingleThread(func) => func.settle(),
+            Schedules::Spill(func) => func.settle(),
+            Schedules::Bloom(func) => func.settle(),
+            Schedules::Steal(pool) => pool.settle()
+        }
+    }
+
+    fn new_generation<H>(&mut self, rules: &Rulebook) -> usize where H: Handler {
+        match self {
+            Schedules::Async(rt) => rt.new_generation::<H>(rules),
+            Schedules::Pool(pool) => pool.new_generation::<H>(rules),
+            Schedules::SingleThread(func) => func.new_generation::<H>(rules),
+            Schedules::Spill(func) => func.new_generation::<H>(rules),
+            Schedules::Bloom(func) => func.new_generation::<H>(rules),
+            Schedules::Steal(pool) => pool.new_generation::<H>(rules)
+        }
+    }
+
+    fn clean_state(&self) -> bool {
+        match self {
+            Schedules::Async(rt) => rt.clean_state(),
+            Schedules::Pool(pool) => pool.clean_state(),
+            Schedules::SingleThread(_func) => true,
+            Schedules::Spill(_func) => true,
+            Schedules::Bloom(_func) => true,
+            Schedules::Steal(pool) => pool.clean_state(),
+        }
+    }
+
+    fn wait_idle(&self) {
+        match self {
+            Schedules::Async(rt) => rt.wait_idle(),
+            Schedules::Pool(pool) => pool.wait_idle(),
+            Schedules::SingleThread(func) => func.wait_idle(),
+            Schedules::Spill(func) => func.wait_idle(),
+            Schedules::Bloom(func) => func.wait_idle(),
+            Schedules::Steal(pool) => pool.wait_idle(),
+        }
+    }
+
+    fn set_cancel(&mut self, token: CancelToken) {
+        match self {
+            Schedules::Async(rt) => rt.set_cancel(token),
+            Schedules::Pool(pool) => pool.set_cancel(token),
+            Schedules::SingleThread(func) => func.set_cancel(token),
+            Schedules::Spill(func) => func.set_cancel(token),
+            Schedules::Bloom(func) => func.set_cancel(token),
+            Schedules::Steal(pool) => pool.set_cancel(token),
+        }
+    }
+
+    fn set_pruner(&mut self, pruner: Arc<dyn Prune>) {
+        match self {
+            Schedules::Async(rt) => rt.set_pruner(pruner),
+            Schedules::Pool(pool) => pool.set_pruner(pruner),
+            Schedules::SingleThread(func) => func.set_pruner(pruner),
+            Schedules::Spill(func) => func.set_pruner(pruner),
+            Schedules::Bloom(func) => func.set_pruner(pruner),
+            Schedules::Steal(pool) => pool.set_pruner(pruner),
+        }
+    }
+
+    fn take_error(&mut self) -> Option<Error> {
+        match self {
+            Schedules::Async(rt) => rt.take_error(),
+            Schedules::Pool(pool) => pool.take_error(),
+            Schedules::SingleThread(func) => func.take_error(),
+            Schedules::Spill(func) => func.take_error(),
+            Schedules::Bloom(func) => func.take_error(),
+            Schedules::Steal(pool) => pool.take_error(),
+        }
+    }
+
+    fn schedule(&mut self, permute: Permutation)  {
+        match self {
+            Schedules::Async(rt) => rt.schedule(permute),
+            Schedules::Pool(pool) => pool.schedule(permute),
+            Schedules::SingleThread(func) => func.schedule(permute),
+            Schedules::Spill(func) => func.schedule(permute),
+            Schedules::Bloom(func) => func.schedule(permute),
+            Schedules::Steal(pool) => pool.schedule(permute),
+        }
+    }
+}
+
+impl Length for Schedules {
+    fn length(&self) -> usize {
+        match self {
+            Schedules::Async(rt) => rt.length(),
+            Schedules::Pool(pool) => pool.length(),
+            Schedules::SingleThread(func) => func.length(),
+            Schedules::Spill(func) => func.length(),
+            Schedules::Bloom(func) => func.length(),
+            Schedules::Steal(pool) => pool.length(),
+        }
+    }
+}
+
+impl From<Schedules> for HashSet<Cell> {
+    fn from(x: Schedules) -> HashSet<Cell> {
+        match x {
+            Schedules::Async(rt) => rt.into(),
+            Schedules::Pool(pool) => pool.into(),
+            Schedules::SingleThread(func) => func.into(),
+            Schedules::Spill(func) => func.into(),
+            Schedules::Bloom(func) => func.into(),
+            Schedules::Steal(pool) => pool.into(),
+        }
+    }
+}
+
+impl Drain for Schedules {
+    fn drain(&mut self) -> Cells {
+        match self {
+            Schedules::Async(rt) => rt.drain(),
+            Schedules::Pool(pool) => pool.drain(),
+            Schedules::SingleThread(func) => func.drain(),
+            Schedules::Spill(func) => func.drain(),
+            Schedules::Bloom(func) => func.drain(),
+            Schedules::Steal(pool) => pool.drain(),
+        }
+    }
+}
+
+impl Snapshot for Schedules {
+    fn fresh(&mut self) -> Cells {
+        match self {
+            Schedules::Async(rt) => rt.fresh(),
+            Schedules::Pool(pool) => pool.fresh(),
+            Schedules::SingleThread(func) => func.fresh(),
+            Schedules::Spill(func) => func.fresh(),
+            Schedules::Bloom(func) => func.fresh(),
+            Schedules::Steal(pool) => pool.fresh(),
+        }
+    }
+
+    fn snapshot(&mut self) -> Cells {
+        match self {
+            Schedules::Async(rt) => rt.snapshot(),
+            Schedules::Pool(pool) => pool.snapshot(),
+            Schedules::SingleThread(func) => func.snapshot(),
+            Schedules::Spill(func) => func.snapshot(),
+            Schedules::Bloom(func) => func.snapshot(),
+            Schedules::Steal(pool) => pool.snapshot(),
+        }
+    }
+}
+
+/// Everything a run is made of, checked together by `build`,
+/// which saves choosing a scheduler and a handler type by hand
+#[derive(Debug, Default)]
+pub struct GeneratorBuilder {
+    presets: Vec<Rulebook>,
+    rules: Vec<(u8, RuleCell)>,
+    merge: Merge,
+    closure: Closure,
+    pattern: Pattern,
+    order: Order,
+    policy: Option<Policy>,
+    filters: Vec<Arc<dyn Filter>>,
+    model: Option<Arc<Model>>,
+    seeds: Vec<Cell>,
+    max_candidates: Option<u64>,
+    max_time: Option<Duration>,
+    shard: Option<Shard>,
+    dedup: Dedup,
+    threads: Option<usize>,
+    scheduler: Option<Schedules>,
+    cancel: Option<CancelToken>,
+}
+
+impl GeneratorBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// rules merged in before those of `rule`, in the order they're given
+    pub fn preset(mut self, rules: Rulebook) -> Self {
+        self.presets.push(rules);
+        self
+    }
+
+    pub fn rule<R>(mut self, from: u8, to: R) -> Self
+    where R: Into<RuleCell>
+    {
+        self.rules.push((from, to.into()));
+        self
+    }
+
+    pub fn rules<I>(mut self, rules: I) -> Self
+    where I: IntoIterator<Item=(u8, RuleCell)>
+    {
+        self.rules.extend(rules);
+        self
+    }
+
+    /// how presets and rules for the same character combine, see `Rulebook::add`
+    pub fn merge(mut self, merge: Merge) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    pub fn closure(mut self, closure: Closure) -> Self {
+        self.closure = closure;
+        self
+    }
+
+    /// the handler deciding which positions are permutated
+    pub fn pattern(mut self, pattern: Pattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// on top of any added before, see `Generator::add_filter`
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where F: Filter + 'static
+    {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    /// scores candidates for `Order::Likelihood`
+    pub fn model(mut self, model: Arc<Model>) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    pub fn seed<I>(mut self, seed: I) -> Self
+    where I: Into<Cell>
+    {
+        self.seeds.push(seed.into());
+        self
+    }
+
+    pub fn seeds<I, S>(mut self, seeds: I) -> Self
+    where
+        I: IntoIterator<Item=S>,
+        S: Into<Cell>
+    {
+        self.seeds.extend(seeds.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn max_candidates(mut self, max: u64) -> Self {
+        self.max_candidates = Some(max);
+        self
+    }
+
+    /// cancels the run once it's taken this long
+    pub fn max_time(mut self, max: Duration) -> Self {
+        self.max_time = Some(max);
+        self
+    }
+
+    /// only `shard`'s slice of the keyspaces, see `Generator::shard`
+    pub fn shard(mut self, shard: Shard) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+
+    pub fn dedup(mut self, dedup: Dedup) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// threads permutating candidates, only exact dedup uses more than one
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// runs on `scheduler` rather than one made from `dedup` and `threads`
+    pub fn scheduler(mut self, scheduler: Schedules) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// stops the run once `token` is cancelled
+    pub fn cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// checks the settings against each other and sets the generator up
+    pub fn build(self) -> Result<Run, Error> {
+        self.build_with(|gen, seeds| {
+            seeds.into_iter().for_each(|x| gen.seed(x));
+            Ok(())
+        })
+    }
+
+    /// like `build`, but hands the seeds to `start` rather than seeding
+    /// the generator, so a run can carry on through `Generator::resume`
+    pub fn build_with<F, E>(self, start: F) -> Result<Run, E>
+    where
+        F: FnOnce(&mut Generator<Schedules>, Vec<Cell>) -> Result<(), E>,
+        E: From<Error>
+    {
+        let (mut run, seeds) = self.prepare()?;
+        start(&mut run.gen, seeds)?;
+        Ok(run)
+    }
+
+    fn prepare(self) -> Result<(Run, Vec<Cell>), Error> {
+        let invalid = |x: &str| Err(Error::InvalidConfig(x.into()));
+        let threads = self.threads.unwrap_or(1);
+
+        if self.seeds.is_empty() {
+            return invalid("a run needs at least one seed")
+        }
+        if self.scheduler.is_some() && (self.threads.is_some() || self.dedup != Dedup::Exact) {
+            return invalid("a scheduler given already decides on dedup and threads")
+        }
+        if threads == 0 {
+            return invalid("a run needs at least one thread")
+        }
+        if threads > 1 && self.dedup != Dedup::Exact {
+            return invalid("spill and bloom dedup only run on a single thread")
+        }
+        if let Dedup::Bloom { error, retain, .. } = self.dedup {
+            if !(error > 0.0 && error < 1.0) {
+                return invalid("bloom false positive rate has to be between 0 and 1")
+            }
+            if self.order.is_buffered() && !retain {
+                return invalid("an ordered run needs the bloom filter to retain its candidates")
+            }
+        }
+        if self.order == Order::Likelihood && self.model.is_none() {
+            return invalid("Order::Likelihood needs a model")
+        }
+        if self.shard.is_some() && !matches!(self.order, Order::Unordered | Order::Index) {
+            return invalid("shards are handed out by index, so they can't be ordered otherwise")
+        }
+        if self.policy.as_ref().is_some_and(|x| x.min_len > x.max_len) {
+            return invalid("policy's min_len is above its max_len")
+        }
+
+        let mut rules = Rulebook::from(Vec::new());
+        for preset in &self.presets {
+            rules.merge(preset, self.merge)?;
+        }
+        for (from, to) in self.rules {
+            if to.is_empty() {
+                return Err(Error::EmptyRule(from))
+            }
+            rules.add(from, to, self.merge)?;
+        }
+
+        let scheduler = match self.scheduler {
+            Some(scheduler) => scheduler,
+            None => Schedules::new(threads, &self.dedup)?
+        };
+        let mut gen = Generator::new(rules, scheduler);
+        gen.set_closure(self.closure);
+        gen.set_order(self.order);
+        if let Some(policy) = self.policy {
+            gen.set_policy(policy);
+        }
+        gen.filters.extend(self.filters);
+        if let Some(model) = self.model {
+            gen.set_model(model);
+        }
+
+        let cancel = self.cancel.unwrap_or_default();
+        gen.set_cancel(cancel.clone());
+
+        let run = Run {
+            gen,
+            pattern: self.pattern,
+            cancel,
+            max_candidates: self.max_candidates.unwrap_or(u64::MAX),
+            max_time: self.max_time,
+            shard: self.shard,
+        };
+        Ok((run, self.seeds))
+    }
+}
+
+/// Where a `Run` hands its candidates
+pub trait Sink {
+    fn item(&mut self, item: Cell);
+
+    /// called once each generation's candidates were handed out,
+    /// an error stops the run
+    fn generation(&mut self, _gen: &mut Generator<Schedules>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F> Sink for F
+where F: FnMut(Cell)
+{
+    fn item(&mut self, item: Cell) {
+        self(item)
+    }
+}
+
+/// A generator `GeneratorBuilder` has set up, ready to run
+#[derive(Debug)]
+pub struct Run {
+    gen: Generator<Schedules>,
+    pattern: Pattern,
+    cancel: CancelToken,
+    max_candidates: u64,
+    max_time: Option<Duration>,
+    shard: Option<Shard>,
+}
+
+impl Run {
+    pub fn generator(&self) -> &Generator<Schedules> {
+        &self.gen
+    }
+
+    /// the token which stops this run, as set with `GeneratorBuilder::cancel`
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// the generator, for driving it by hand.
+    /// `max_candidates`, `max_time` and `shard` are left behind
+    pub fn into_generator(self) -> Generator<Schedules> {
+        self.gen
+    }
+
+    /// hands every candidate to `sink` until there are no more,
+    /// a limit is reached or the run is cancelled.
+    /// the generator is handed back for its progress and counts,
+    /// unless its storage failed along the way
+    pub fn run<S>(mut self, mut sink: S) -> Result<Generator<Schedules>, Error>
+    where S: Sink
+    {
+        // dropping `_timer` lets the timer go without cancelling
+        let _timer = self.max_time.map(|budget| {
+            let (timer, expired) = mpsc::channel::<()>();
+            let cancel = self.cancel.clone();
+            std::thread::spawn(move || {
+                if let Err(mpsc::RecvTimeoutError::Timeout) = expired.recv_timeout(budget) {
+                    cancel.cancel();
+                }
+            });
+            timer
+        });
+
+        let mut candidates: u64 = 0;
+        if let Some(shard) = self.shard {
+            let items = match self.pattern {
+                Pattern::Const => self.gen.shard::<ConstPattern>(shard),
+                Pattern::Modulo => self.gen.shard::<ModulusPattern>(shard),
+            };
+            for item in items {
+                if candidates >= self.max_candidates || self.cancel.is_cancelled() {
+                    break
+                }
+                sink.item(item);
+                candidates += 1;
+            }
+            return Ok(self.gen)
+        }
+
+        let max_candidates = self.max_candidates;
+        let mut emit = |gen: &mut Generator<Schedules>, sink: &mut S| {
+            let left = (max_candidates - candidates).min(usize::MAX as u64) as usize;
+            candidates += gen.emit_at_most(left, |x| sink.item(x)) as u64;
+            candidates >= max_candidates
+        };
+
+        let mut spent = emit(&mut self.gen, &mut sink);
+        while !spent && !self.gen.is_cancelled() {
+            let last = self.gen.length();
+            match self.pattern {
+                Pattern::Const => self.gen.new_generation::<ConstPattern>()?,
+                Pattern::Modulo => self.gen.new_generation::<ModulusPattern>()?,
+            }
+            if let Some(e) = self.gen.take_error() {
+                return Err(e)
+            }
+            spent = emit(&mut self.gen, &mut sink);
+            sink.generation(&mut self.gen)?;
+
+            if self.gen.length().saturating_sub(last) == 0 && self.gen.done() { break }
+        }
+
+        if self.gen.is_cancelled() && !spent {
+            self.gen.release();
+            emit(&mut self.gen, &mut sink);
+        }
+
+        match self.gen.take_error() {
+            Some(e) => Err(e),
+            None => Ok(self.gen)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::policy::Class;
+
+    fn builder() -> GeneratorBuilder {
+        GeneratorBuilder::new()
+            .preset("a:4@\ns:5$".parse().unwrap())
+            .rule(b'o', &b"0"[..])
+            .seeds(["pass", "solo"].iter().map(|x| x.as_bytes()))
+    }
+
+    fn run(builder: GeneratorBuilder) -> Vec<Cell> {
+        let mut found = Vec::new();
+        builder.build().unwrap().run(|x| found.push(x)).unwrap();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn behavior_builder() {
+        let rules: Rulebook = "a:4@\ns:5$\no:0".parse().unwrap();
+        let mut gen = Generator::new(rules, SingleThread::new());
+        gen.set_policy(Policy { min_len: 0, max_len: usize::MAX, require: vec![Class::Digit] });
+        gen.seed(&b"pass"[..]);
+        gen.seed(&b"solo"[..]);
+        let mut expected = Vec::new();
+        for _ in 0..8 {
+            gen.emit(|x| expected.push(x));
+            gen.new_generation::<ModulusPattern>().unwrap();
+        }
+        gen.emit(|x| expected.push(x));
+        expected.sort();
+
+        let digit = || builder().policy(Policy { require: vec![Class::Digit], ..Policy::default() });
+        assert_eq!(run(digit()), expected);
+        assert_eq!(run(digit().threads(3)), expected);
+        assert_eq!(run(digit().dedup(Dedup::Bloom { capacity: 1 << 10, error: 1e-6, retain: false })), expected);
+        assert_eq!(run(digit().scheduler(Schedules::Pool(MultithreadMutex::new(2)))), expected);
+
+        let limited = builder().max_candidates(5).build().unwrap().run(|_| {}).unwrap();
+        assert_eq!(limited.progress().emitted, 5);
+
+        let mut sharded = Vec::new();
+        for index in 1..=3 {
+            sharded.extend(run(builder().shard(Shard::new(index, 3).unwrap())));
+        }
+        sharded.sort();
+        assert_eq!(sharded, run(builder()));
+    }
+
+    /// keeps the generations it's told about, refusing `stop`
+    #[derive(Default)]
+    struct Generations {
+        found: Vec<Cell>,
+        seen: Vec<usize>,
+        stop: Option<usize>,
+    }
+
+    impl Sink for &mut Generations {
+        fn item(&mut self, item: Cell) {
+            self.found.push(item);
+        }
+
+        fn generation(&mut self, gen: &mut Generator<Schedules>) -> Result<(), Error> {
+            self.seen.push(gen.generation());
+            match self.stop == Some(gen.generation()) {
+                true => Err(Error::InvalidConfig("stop".into())),
+                false => Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn run_generations() {
+        let mut sink = Generations::default();
+        let gen = builder().build().unwrap().run(&mut sink).unwrap();
+        assert_eq!(sink.seen, (1..=gen.generation()).collect::<Vec<_>>());
+        assert_eq!(sink.found.len() as u128, gen.progress().emitted);
+
+        // resumed, the seeds are left to `start`
+        let run = builder().build_with(|gen, seeds| -> Result<(), Error> {
+            assert_eq!(seeds.len(), 2);
+            gen.seed(seeds[0].clone());
+            Ok(())
+        }).unwrap();
+        let mut sink = Generations { stop: Some(3), ..Default::default() };
+        assert!(matches!(run.run(&mut sink), Err(Error::InvalidConfig(_))));
+        assert_eq!(sink.seen, vec![1, 2, 3]);
+        assert!(sink.found.iter().all(|x| x.len() == 4 && x[0] == b'p'));
+    }
+
+    #[test]
+    fn run_stops_on_storage_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let spill = Spill::new_in(0, dir.path()).unwrap();
+        std::fs::remove_dir_all(dir.path()).unwrap();
+
+        let run = builder().scheduler(Schedules::Spill(SingleThread::with_storage(spill))).build().unwrap();
+        let mut sink = Generations::default();
+        assert!(matches!(run.run(&mut sink), Err(Error::Io(_))));
+        assert!(sink.seen.is_empty());
+    }
+
+    #[test]
+    fn builder_validates() {
+        let invalid = |x: GeneratorBuilder| matches!(x.build(), Err(Error::InvalidConfig(_)));
+        assert!(invalid(GeneratorBuilder::new()));
+        assert!(invalid(builder().threads(0)));
+        assert!(invalid(builder().threads(2).dedup(Dedup::Bloom { capacity: 10, error: 0.01, retain: false })));
+        assert!(invalid(builder().dedup(Dedup::Bloom { capacity: 10, error: 1.5, retain: false })));
+        assert!(invalid(builder().order(Order::Lexicographic).dedup(Dedup::Bloom { capacity: 10, error: 0.01, retain: false })));
+        assert!(invalid(builder().order(Order::Likelihood)));
+        assert!(invalid(builder().order(Order::Lexicographic).shard(Shard::new(1, 2).unwrap())));
+        assert!(invalid(builder().policy(Policy { min_len: 9, max_len: 8, require: Vec::new() })));
+        assert!(invalid(builder().scheduler(Schedules::SingleThread(SingleThread::new())).threads(2)));
+
+        assert!(matches!(builder().rule(b'a', RuleCell::new()).build(), Err(Error::EmptyRule(b'a'))));
+        assert!(matches!(builder().merge(Merge::Strict).rule(b'a', &b"^"[..]).build(), Err(Error::Conflict(_))));
+        assert!(builder().threads(4).build().is_ok());
+    }
+}
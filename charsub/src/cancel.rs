@@ -0,0 +1,24 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering}
+};
+
+/// Shared flag asking a run to stop, clones all refer to the same flag.
+/// Schedulers check it between candidates, so a cancelled generation
+/// returns early with whatever it had found until then.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
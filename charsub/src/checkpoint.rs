@@ -0,0 +1,409 @@
+use crate::{Cell, closure::Closure};
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::Path
+};
+
+const MAGIC: &str = "charsub checkpoint 3";
+
+/// What a run needs to carry on where it left off.
+///
+/// On disk this is a short plain text header, followed by the seeds,
+/// the candidates found but not yet emitted, and every candidate found,
+/// each prefixed by its length as a little endian `u32`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// `Rulebook::fingerprint` of the rules in use
+    pub rules: u64,
+    /// name of the handler in use
+    pub pattern: String,
+    pub closure: Closure,
+    pub generation: usize,
+    pub seeds: Vec<Cell>,
+    /// found, but not yet emitted when the run stopped
+    pub pending: Vec<Cell>,
+    /// bytes of output written when the checkpoint was made
+    pub output: u64,
+}
+
+impl Checkpoint {
+    /// writes the checkpoint next to `path` first, and only
+    /// then moves it over, so an interrupted write leaves
+    /// the previous checkpoint intact
+    pub fn write<P, I>(&self, path: P, candidates: I) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item=Cell>
+    {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+
+        let file = File::create(&partial)?;
+        let mut out = BufWriter::new(&file);
+
+        writeln!(out, "{}", MAGIC)?;
+        writeln!(out, "rules {:016x}", self.rules)?;
+        writeln!(out, "pattern {}", self.pattern)?;
+        writeln!(out, "closure {}", self.closure.name())?;
+        writeln!(out, "generation {}", self.generation)?;
+        writeln!(out, "seeds {}", self.seeds.len())?;
+        writeln!(out, "pending {}", self.pending.len())?;
+        writeln!(out, "output {}", self.output)?;
+        writeln!(out)?;
+
+        for item in self.seeds.iter().chain(&self.pending).cloned().chain(candidates) {
+            out.write_all(&(item.len() as u32).to_le_bytes())?;
+            out.write_all(&item)?;
+        }
+
+        out.flush()?;
+        drop(out);
+        file.sync_all()?;
+        fs::rename(&partial, path)
+    }
+
+    /// reads the checkpoint's header, the candidates
+    /// it holds are read as they're iterated
+    pub fn read<P>(path: P) -> io::Result<(Self, Records)>
+    where P: AsRef<Path>
+    {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut line = String::new();
+
+        input.read_line(&mut line)?;
+        if line.trim_end() != MAGIC {
+            return Err(invalid("not a charsub checkpoint"))
+        }
+
+        let mut rules = None;
+        let mut pattern = None;
+        let mut closure = None;
+        let mut generation = None;
+        let mut seeds = None;
+        let mut pending = Some(0);
+        let mut output = None;
+
+        loop {
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                return Err(invalid("checkpoint header is cut short"))
+            }
+
+            let line = line.trim_end();
+            if line.is_empty() {
+                break
+            }
+
+            let (key, value) = line.split_once(' ').ok_or_else(|| invalid("malformed checkpoint header"))?;
+            match key {
+                "rules" => rules = u64::from_str_radix(value, 16).ok(),
+                "pattern" => pattern = Some(value.to_string()),
+                "closure" => closure = value.parse().ok(),
+                "generation" => generation = value.parse().ok(),
+                "seeds" => seeds = value.parse::<usize>().ok(),
+                "pending" => pending = value.parse::<usize>().ok(),
+                "output" => output = value.parse().ok(),
+                _ => return Err(invalid("unknown checkpoint header field"))
+            }
+        }
+
+        let mut records = Records(Some(input));
+        let seeds = records.next_records(seeds.ok_or_else(|| invalid("checkpoint is missing its seeds"))?)?;
+        let pending = records.next_records(pending.ok_or_else(|| invalid("malformed pending count"))?)?;
+
+        let checkpoint = Checkpoint {
+            rules: rules.ok_or_else(|| invalid("checkpoint is missing its rules"))?,
+            pattern: pattern.ok_or_else(|| invalid("checkpoint is missing its pattern"))?,
+            closure: closure.ok_or_else(|| invalid("checkpoint is missing its closure"))?,
+            generation: generation.ok_or_else(|| invalid("checkpoint is missing its generation"))?,
+            seeds,
+            pending,
+            output: output.ok_or_else(|| invalid("checkpoint is missing its output offset"))?
+        };
+
+        Ok((checkpoint, records))
+    }
+
+    /// opens the output the run was writing to, cut back to where it
+    /// was when the checkpoint was made. Anything written after
+    /// that is emitted again once resumed, so it mustn't stay
+    pub fn reopen_output<P>(&self, path: P) -> io::Result<File>
+    where P: AsRef<Path>
+    {
+        let file = OpenOptions::new().append(true).open(path)?;
+        if file.metadata()?.len() < self.output {
+            return Err(invalid("output is shorter than when the checkpoint was made"))
+        }
+        file.set_len(self.output)?;
+        Ok(file)
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Candidates a checkpoint holds, read as they're iterated.
+/// Iteration ends after the first one which can't be read
+pub struct Records(Option<BufReader<File>>);
+
+impl Records {
+    fn next_record(&mut self) -> io::Result<Option<Cell>> {
+        let input = match &mut self.0 {
+            Some(input) => input,
+            None => return Ok(None)
+        };
+
+        let mut len = [0; 4];
+        match input.read_exact(&mut len) {
+            Ok(()) => {},
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e)
+        }
+
+        let mut item = Cell::from_elem(0, u32::from_le_bytes(len) as usize);
+        input.read_exact(&mut item)?;
+        Ok(Some(item))
+    }
+
+    fn next_records(&mut self, n: usize) -> io::Result<Vec<Cell>> {
+        (0..n)
+            .map(|_| self.next_record()?.ok_or_else(|| invalid("checkpoint is cut short")))
+            .collect()
+    }
+}
+
+impl Iterator for Records {
+    type Item = io::Result<Cell>;
+
+    fn next(&mut self) -> Option<io::Result<Cell>> {
+        let record = self.next_record();
+        if record.is_err() {
+            self.0 = None;
+        }
+        record.transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        CancelToken, Generator,
+        patterns::ConstPattern,
+        scheduler::SingleThread,
+        testutil::{generations, leet as rules}
+    };
+    use hashbrown::HashSet;
+
+    #[test]
+    fn resume_behave() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.ckpt");
+
+        let mut full = Generator::new(rules(), SingleThread::new());
+        full.seed(&b"password"[..]);
+        let expected = generations::<ConstPattern, _>(&mut full, 6);
+
+        let mut first = Generator::new(rules(), SingleThread::new());
+        first.seed(&b"password"[..]);
+        let mut found = generations::<ConstPattern, _>(&mut first, 2);
+
+        let checkpoint = Checkpoint {
+            rules: first.map().fingerprint(),
+            pattern: "const".into(),
+            closure: Closure::Chained,
+            generation: 2,
+            seeds: vec![Cell::from(&b"password"[..])],
+            pending: Vec::new(),
+            output: 0
+        };
+        checkpoint.write(&path, first.snapshot()).unwrap();
+        drop(first);
+
+        let (read, candidates) = Checkpoint::read(&path).unwrap();
+        assert_eq!(read, checkpoint);
+
+        let mut resumed = Generator::new(rules(), SingleThread::new());
+        assert_eq!(read.rules, resumed.map().fingerprint());
+        resumed.resume(read.seeds.clone(), read.pending.clone(), candidates.map(Result::unwrap));
+        assert_eq!(resumed.emit(|x| found.push(x)), 0);
+        for _ in 2..6 {
+            resumed.new_generation::<ConstPattern>().unwrap();
+            resumed.emit(|x| found.push(x));
+        }
+
+        assert_eq!(found.len(), expected.len());
+        assert_eq!(
+            found.into_iter().collect::<HashSet<_>>(),
+            expected.into_iter().collect::<HashSet<_>>()
+        );
+    }
+
+    #[test]
+    fn resume_pending() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.ckpt");
+
+        let mut first = Generator::new(rules(), SingleThread::new());
+        first.seed(&b"password"[..]);
+        let mut found = Vec::new();
+        first.emit(|x| found.push(x));
+        first.new_generation::<ConstPattern>().unwrap();
+        assert_eq!(first.emit_at_most(2, |x| found.push(x)), 2);
+
+        Checkpoint {
+            rules: first.map().fingerprint(),
+            pattern: "const".into(),
+            closure: Closure::Chained,
+            generation: 1,
+            seeds: vec![Cell::from(&b"password"[..])],
+            pending: first.unemitted().collect(),
+            output: 0
+        }.write(&path, first.snapshot()).unwrap();
+
+        let (read, candidates) = Checkpoint::read(&path).unwrap();
+        assert_eq!(read.pending.len(), 6);
+
+        let mut resumed = Generator::new(rules(), SingleThread::new());
+        resumed.resume(read.seeds, read.pending, candidates.map(Result::unwrap));
+        assert_eq!(resumed.emit(|x| found.push(x)), 6);
+        for _ in 1..6 {
+            resumed.new_generation::<ConstPattern>().unwrap();
+            resumed.emit(|x| found.push(x));
+        }
+
+        let unique: HashSet<_> = found.iter().cloned().collect();
+        assert_eq!(unique.len(), found.len());
+        assert_eq!(found.len() as u128, resumed.progress().keyspace.unwrap());
+    }
+
+    #[test]
+    fn resume_after_cancel() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.ckpt");
+
+        let mut full = Generator::new(rules(), SingleThread::new());
+        full.seed(&b"password"[..]);
+        let expected = generations::<ConstPattern, _>(&mut full, 6);
+
+        let cancel = CancelToken::new();
+        let mut first = Generator::new(rules(), SingleThread::new());
+        first.set_cancel(cancel.clone());
+        first.seed(&b"password"[..]);
+        let mut found = generations::<ConstPattern, _>(&mut first, 1);
+
+        // takes the first generation's candidates without reaching any
+        cancel.cancel();
+        first.new_generation::<ConstPattern>().unwrap();
+        assert_eq!(first.emit(|x| found.push(x)), 0);
+
+        Checkpoint {
+            rules: first.map().fingerprint(),
+            pattern: "const".into(),
+            closure: Closure::Chained,
+            generation: 2,
+            seeds: vec![Cell::from(&b"password"[..])],
+            pending: first.unemitted().collect(),
+            output: 0
+        }.write(&path, first.snapshot()).unwrap();
+
+        let (read, candidates) = Checkpoint::read(&path).unwrap();
+        let mut resumed = Generator::new(rules(), SingleThread::new());
+        resumed.resume(read.seeds, read.pending, candidates.map(Result::unwrap));
+        // the cancelled generation found nothing, so it's run again
+        for _ in 1..6 {
+            resumed.new_generation::<ConstPattern>().unwrap();
+            resumed.emit(|x| found.push(x));
+        }
+
+        assert_eq!(found.len(), expected.len());
+        assert_eq!(
+            found.into_iter().collect::<HashSet<_>>(),
+            expected.into_iter().collect::<HashSet<_>>()
+        );
+    }
+
+    fn write_all(out: &mut File, gen: &mut Generator<SingleThread>) {
+        gen.emit(|x| {
+            out.write_all(&x).unwrap();
+            out.write_all(b"\n").unwrap();
+        });
+    }
+
+    #[test]
+    fn resume_after_kill() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, output) = (dir.path().join("state.ckpt"), dir.path().join("out.txt"));
+
+        let mut first = Generator::new(rules(), SingleThread::new());
+        first.seed(&b"password"[..]);
+        let mut out = File::create(&output).unwrap();
+        write_all(&mut out, &mut first);
+        first.new_generation::<ConstPattern>().unwrap();
+        write_all(&mut out, &mut first);
+
+        Checkpoint {
+            rules: first.map().fingerprint(),
+            pattern: "const".into(),
+            closure: Closure::Chained,
+            generation: 1,
+            seeds: vec![Cell::from(&b"password"[..])],
+            pending: first.unemitted().collect(),
+            output: out.metadata().unwrap().len()
+        }.write(&path, first.snapshot()).unwrap();
+
+        // killed partway to the next checkpoint, after writing more
+        first.new_generation::<ConstPattern>().unwrap();
+        write_all(&mut out, &mut first);
+        drop((first, out));
+
+        let (read, candidates) = Checkpoint::read(&path).unwrap();
+        let mut resumed = Generator::new(rules(), SingleThread::new());
+        resumed.set_generation(read.generation);
+        let mut out = read.reopen_output(&output).unwrap();
+        resumed.resume(read.seeds, read.pending, candidates.map(Result::unwrap));
+        write_all(&mut out, &mut resumed);
+        for _ in 1..6 {
+            resumed.new_generation::<ConstPattern>().unwrap();
+            write_all(&mut out, &mut resumed);
+        }
+
+        let written = fs::read(&output).unwrap();
+        let lines: Vec<&[u8]> = written.split(|x| *x == b'\n').filter(|x| !x.is_empty()).collect();
+        let unique: HashSet<_> = lines.iter().collect();
+        assert_eq!(unique.len(), lines.len());
+        assert_eq!(lines.len() as u128, resumed.progress().keyspace.unwrap());
+    }
+
+    #[test]
+    fn corrupt_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.ckpt");
+
+        let mut gen = Generator::new(rules(), SingleThread::new());
+        gen.seed(&b"password"[..]);
+        Checkpoint {
+            rules: gen.map().fingerprint(),
+            pattern: "const".into(),
+            closure: Closure::Chained,
+            generation: 0,
+            seeds: vec![Cell::from(&b"password"[..])],
+            pending: Vec::new(),
+            output: 0
+        }.write(&path, gen.snapshot()).unwrap();
+
+        // a record claiming more bytes than are left
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&64u32.to_le_bytes()).unwrap();
+        file.write_all(b"cut").unwrap();
+
+        let (_, mut candidates) = Checkpoint::read(&path).unwrap();
+        assert_eq!(candidates.next().unwrap().unwrap(), Cell::from(&b"password"[..]));
+        assert!(candidates.next().unwrap().is_err());
+        assert!(candidates.next().is_none());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    Cell,
+    RuleCell,
+    Rulebook,
+    analyse::chains,
+    keyspace::{Keyspace, reach, hop},
+    patterns::{Filter, Handler, Prune},
+    unit::Permutation
+};
+
+use hashbrown::HashMap;
+use serde::{Serialize, Deserialize};
+
+/// How far rules carry a seed's characters.
+///
+/// Each generation substitutes whatever the last one did, so
+/// `a:b b:c` turns `a` into `c` over two generations unless told otherwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Closure {
+    /// rules apply to substituted characters as well, one step per generation
+    #[default]
+    Chained,
+    /// rules only apply to the seed's own characters, `a:b b:c` never turns `a` into `c`
+    SingleHop,
+    /// rules are replaced up front by every character each one reaches,
+    /// `a:b b:c` turns `a` into `c` in a single generation
+    Transitive,
+}
+
+impl std::str::FromStr for Closure {
+    type Err = String;
+
+    fn from_str(x: &str) -> Result<Self, String> {
+        Ok(match x.trim().to_ascii_lowercase().as_str() {
+            "chained" => Closure::Chained,
+            "single-hop" | "single" => Closure::SingleHop,
+            "transitive" | "closure" => Closure::Transitive,
+            _ => return Err(format!("Expected chained, single-hop or transitive, got '{}'", x))
+        })
+    }
+}
+
+impl Closure {
+    /// name it's parsed from
+    pub fn name(&self) -> &'static str {
+        match self {
+            Closure::Chained => "chained",
+            Closure::SingleHop => "single-hop",
+            Closure::Transitive => "transitive",
+        }
+    }
+}
+
+impl Rulebook {
+    /// rules turning each character into everything it
+    /// reaches through any chain of these rules
+    pub fn closure(&self) -> Rulebook {
+        let mut rules = Rulebook(HashMap::new());
+        for from in self.0.keys() {
+            let to: RuleCell = reach(*from, self).into_iter().skip(1).collect();
+            if !to.is_empty() {
+                rules.set(*from, to);
+            }
+        }
+        rules
+    }
+
+    /// characters which turn back into themselves through other characters,
+    /// as the shortest chain around. `a:4 4:a` is `[a, 4]`, and
+    /// characters already part of one aren't listed again
+    pub fn cycles(&self) -> Vec<Vec<u8>> {
+        let mut keys: Vec<u8> = self.0.keys().copied().collect();
+        keys.sort_unstable();
+
+        let mut found: Vec<Vec<u8>> = Vec::new();
+        for from in keys {
+            if found.iter().any(|x| x.contains(&from)) {
+                continue
+            }
+
+            // the shortest chain back to `from` through something else
+            let cycle = chains(from, self).into_iter()
+                .filter(|(byte, _)| *byte != from && self.alternatives(*byte).is_some_and(|x| x.contains(&from)))
+                .map(|(byte, mut chain)| {
+                    chain.push(byte);
+                    chain
+                })
+                .min_by(|x, y| x.len().cmp(&y.len()).then(x.cmp(y)));
+
+            if let Some(cycle) = cycle {
+                found.push(cycle);
+            }
+        }
+        found
+    }
+}
+
+/// Keeps candidates within the seeds' `Keyspace::single_hop`,
+/// both from being emitted and from being permutated
+#[derive(Debug, Clone)]
+pub struct Hops {
+    rules: Rulebook,
+    handle: fn(&Permutation) -> bool,
+    keyspaces: Vec<Keyspace>,
+    /// for each byte, one of those rules connect it to. substitutions never
+    /// change it, so a candidate only needs checking against seeds sharing it
+    component: Vec<u8>,
+    /// keyspaces by the components of their seed's characters
+    index: HashMap<Cell, Vec<usize>>,
+}
+
+impl Hops {
+    pub fn new<H: Handler>(rules: &Rulebook) -> Self {
+        let mut component: Vec<u8> = (0..=255).collect();
+        fn root(component: &[u8], mut byte: u8) -> u8 {
+            while component[byte as usize] != byte {
+                byte = component[byte as usize];
+            }
+            byte
+        }
+        for (from, entry) in &rules.0 {
+            for to in RuleCell::from(entry.clone()) {
+                let (x, y) = (root(&component, *from), root(&component, to));
+                component[x.max(y) as usize] = x.min(y);
+            }
+        }
+        for byte in 0..=255 {
+            component[byte as usize] = root(&component, byte);
+        }
+
+        Self {
+            rules: rules.clone(),
+            handle: H::handle,
+            keyspaces: Vec::new(),
+            component,
+            index: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, seed: &Cell) {
+        let key = self.key(seed);
+        self.index.entry(key).or_default().push(self.keyspaces.len());
+        self.keyspaces.push(Keyspace::build(seed, &self.rules, self.handle, hop));
+    }
+
+    /// whether some seed turns into `item` substituting only its own characters
+    pub fn contains(&self, item: &[u8]) -> bool {
+        self.index.get(&self.key(item))
+            .is_some_and(|x| x.iter().any(|idx| self.keyspaces[*idx].index_of(item).is_some()))
+    }
+
+    fn key(&self, item: &[u8]) -> Cell {
+        item.iter().map(|x| self.component[*x as usize]).collect()
+    }
+}
+
+impl Filter for Hops {
+    fn keep(&self, item: &[u8]) -> bool {
+        self.contains(item)
+    }
+}
+
+/// Whatever a candidate outside the keyspaces turns into stays outside,
+/// as each of their candidates is reached substituting only seed characters
+impl Prune for Hops {
+    fn viable(&self, item: &[u8], _rules: &Rulebook, _handle: fn(&Permutation) -> bool) -> bool {
+        self.contains(item)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Generator,
+        patterns::{ConstPattern, ModulusPattern},
+        policy::Policy,
+        scheduler::{SingleThread, WorkStealing},
+        testutil::{generations, chains as rules}
+    };
+    use hashbrown::HashSet;
+
+    fn run<T>(mut gen: Generator<T>, seed: &str) -> Vec<Cell>
+    where T: crate::Scheduler + crate::Snapshot + crate::Length
+    {
+        gen.seed(seed.as_bytes());
+        let mut found = generations::<ConstPattern, _>(&mut gen, 8);
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn behavior_closure() {
+        let closure = rules().closure();
+        assert_eq!(closure.alternatives(b'a').map(|x| x.to_vec()), Some(b"bcd".to_vec()));
+        assert_eq!(closure.alternatives(b'0').map(|x| x.to_vec()), Some(b"Oo".to_vec()));
+
+        assert_eq!(rules().cycles(), vec![b"0Oo".to_vec(), b"5s".to_vec()]);
+        assert!("a:b\nb:c".parse::<Rulebook>().unwrap().cycles().is_empty());
+        assert!("a:a4".parse::<Rulebook>().unwrap().cycles().is_empty());
+
+        let chained = run(Generator::new(rules(), SingleThread::new()), "as");
+        let mut transitive = Generator::new(rules(), SingleThread::new());
+        transitive.set_closure(Closure::Transitive);
+        assert_eq!(run(transitive, "as"), chained);
+
+        let mut single = Generator::new(rules(), WorkStealing::new(2).unwrap());
+        single.set_closure(Closure::SingleHop);
+        let expected: Vec<Cell> = ["a5", "as", "b5", "bs"].iter().map(|x| Cell::from(x.as_bytes())).collect();
+        assert_eq!(run(single, "as"), expected);
+    }
+
+    #[test]
+    fn single_hop_generations() {
+        let seeds = ["soda", "ocs", "bob"];
+        let mut gen = Generator::new(rules(), SingleThread::new());
+        gen.set_closure(Closure::SingleHop);
+        gen.set_policy(Policy { min_len: 3, max_len: 3, require: Vec::new() });
+        seeds.iter().for_each(|x| gen.seed(x.as_bytes()));
+
+        let found = generations::<ModulusPattern, _>(&mut gen, 4);
+
+        let expected: HashSet<Cell> = seeds.iter()
+            .filter(|x| x.len() == 3)
+            .flat_map(|seed| {
+                let keyspace = Keyspace::single_hop::<ModulusPattern>(&Cell::from(seed.as_bytes()), &rules());
+                (0..keyspace.size()).filter_map(move |x| keyspace.get(x))
+            })
+            .collect();
+        assert_eq!(found.len(), expected.len());
+        assert_eq!(found.into_iter().collect::<HashSet<_>>(), expected);
+    }
+}
@@ -0,0 +1,344 @@
+use crate::Cell;
+
+use std::{
+    fs,
+    io,
+    path::Path
+};
+
+use hashbrown::HashMap;
+use rayon::prelude::*;
+use md5::Md5;
+use md4::Md4;
+use sha1::Sha1;
+use sha2::{Sha256, Digest};
+use serde::{Serialize, Deserialize};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
+/// Hash functions candidates can be tested against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    /// md4 over the candidate as utf-16le, as windows stores passwords
+    Ntlm,
+    /// only as `$2b$` crypt, which carries its own salt
+    Bcrypt,
+}
+
+impl Algorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Ntlm => "ntlm",
+            Algorithm::Bcrypt => "bcrypt",
+        }
+    }
+
+    /// digest of `item`, `None` for bcrypt
+    pub fn digest(&self, item: &[u8]) -> Option<Vec<u8>> {
+        Some(match self {
+            Algorithm::Md5 => Md5::digest(item).to_vec(),
+            Algorithm::Sha1 => Sha1::digest(item).to_vec(),
+            Algorithm::Sha256 => Sha256::digest(item).to_vec(),
+            Algorithm::Ntlm => {
+                // candidates are bytes, each one widened to a utf-16 code unit
+                let wide: Vec<u8> = item.iter().flat_map(|x| [*x, 0]).collect();
+                Md4::digest(&wide).to_vec()
+            },
+            Algorithm::Bcrypt => return None
+        })
+    }
+
+    fn digest_len(&self) -> usize {
+        match self {
+            Algorithm::Md5 | Algorithm::Ntlm => 16,
+            Algorithm::Sha1 => 20,
+            Algorithm::Sha256 => 32,
+            Algorithm::Bcrypt => 0,
+        }
+    }
+}
+
+impl std::str::FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(x: &str) -> Result<Self, String> {
+        Ok(match x.to_ascii_lowercase().as_str() {
+            "md5" => Algorithm::Md5,
+            "sha1" => Algorithm::Sha1,
+            "sha256" => Algorithm::Sha256,
+            "ntlm" => Algorithm::Ntlm,
+            "bcrypt" => Algorithm::Bcrypt,
+            _ => return Err(format!("Expected md5, sha1, sha256, ntlm or bcrypt, got '{}'", x))
+        })
+    }
+}
+
+/// A candidate whose hash is one of the targets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub candidate: Cell,
+    /// the target as it was given
+    pub hash: String,
+}
+
+/// How a target is hashed, targets sharing one
+/// are checked against a single hash of each candidate
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Scheme {
+    Plain(Algorithm),
+    /// `salt:hash`, the candidate hashed after its salt
+    Salted(Algorithm, Vec<u8>),
+    /// `$1$`, `$2b$`, `$5$` or `$6$` crypt, by everything before the hash
+    Crypt(String),
+    /// django's `pbkdf2_sha256$iterations$salt$hash`
+    Pbkdf2 { iterations: u32, salt: String },
+}
+
+impl Scheme {
+    /// what targets of this scheme are looked up by
+    fn hash(&self, item: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            Scheme::Plain(algorithm) => algorithm.digest(item),
+            Scheme::Salted(algorithm, salt) => algorithm.digest(&[salt.as_slice(), item].concat()),
+            Scheme::Crypt(setting) => pwhash::unix::crypt(item, setting).ok().map(String::into_bytes),
+            Scheme::Pbkdf2 { iterations, salt } => {
+                let mut digest = vec![0; 32];
+                pbkdf2::pbkdf2_hmac::<Sha256>(item, salt.as_bytes(), *iterations, &mut digest);
+                Some(digest)
+            }
+        }
+    }
+
+    /// recognises formatted hashes on their own, `algorithm`
+    /// says what bare `hash` and `salt:hash` lines are
+    fn parse(algorithm: Option<Algorithm>, hash: &str) -> Result<(Self, Vec<u8>), String> {
+        let bad = |what: &str| format!("'{}' isn't a {} hash", hash, what);
+
+        if let Some(rest) = hash.strip_prefix("pbkdf2_sha256$") {
+            let mut fields = rest.splitn(3, '$');
+            let (iterations, salt, digest) = (fields.next(), fields.next(), fields.next());
+            let iterations = iterations.and_then(|x| x.parse().ok()).ok_or_else(|| bad("pbkdf2_sha256"))?;
+            let digest = digest.and_then(|x| BASE64.decode(x).ok()).ok_or_else(|| bad("pbkdf2_sha256"))?;
+            let salt = salt.unwrap_or_default().to_string();
+            return Ok((Scheme::Pbkdf2 { iterations, salt }, digest))
+        }
+
+        if hash.starts_with('$') {
+            let setting = match hash.split('$').nth(1) {
+                // bcrypt's salt runs straight into its hash
+                Some("2a" | "2b" | "2y") if hash.len() == 60 && hash.is_ascii() => &hash[..29],
+                Some("1" | "5" | "6") => &hash[..hash.rfind('$').unwrap()],
+                _ => return Err(bad("crypt"))
+            };
+            return Ok((Scheme::Crypt(setting.to_string()), hash.as_bytes().to_vec()))
+        }
+
+        let algorithm = match algorithm {
+            Some(Algorithm::Bcrypt) => return Err(bad("bcrypt")),
+            Some(x) => x,
+            None => return Err(format!("'{}' could be any hash, say which with --algo", hash))
+        };
+        let (scheme, digest) = match hash.rsplit_once(':') {
+            Some((salt, digest)) => (Scheme::Salted(algorithm, salt.as_bytes().to_vec()), digest),
+            None => (Scheme::Plain(algorithm), hash)
+        };
+
+        match decode_hex(digest) {
+            Some(digest) if digest.len() == algorithm.digest_len() => Ok((scheme, digest)),
+            _ => Err(bad(algorithm.name()))
+        }
+    }
+}
+
+/// Hashes to look for, grouped by salt so each candidate is hashed
+/// once per salt, and then costs a single lookup per group
+#[derive(Debug, Clone, Default)]
+pub struct Targets {
+    groups: HashMap<Scheme, HashMap<Vec<u8>, String>>,
+}
+
+impl Targets {
+    /// one hash per line, either hex encoded, `salt:hash`, crypt's
+    /// `$1$`, `$2b$`, `$5$` and `$6$` or django's `pbkdf2_sha256$`.
+    /// `algorithm` is only needed for the first two
+    pub fn new<'a, I>(algorithm: Option<Algorithm>, hashes: I) -> Result<Self, String>
+    where I: IntoIterator<Item=&'a str>
+    {
+        let mut targets = Targets::default();
+        for hash in hashes.into_iter().map(str::trim).filter(|x| !x.is_empty()) {
+            let (scheme, digest) = Scheme::parse(algorithm, hash)?;
+            targets.groups.entry(scheme)
+                .or_default()
+                .insert(digest, hash.to_string());
+        }
+        Ok(targets)
+    }
+
+    pub fn load<P>(algorithm: Option<Algorithm>, path: P) -> io::Result<Self>
+    where P: AsRef<Path>
+    {
+        let text = fs::read_to_string(path)?;
+        Self::new(algorithm, text.lines())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// amount of distinct salts, each one hashes every candidate again
+    pub fn salts(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// the targets `item` hashes to
+    pub fn check<'a>(&'a self, item: &'a [u8]) -> impl Iterator<Item=&'a str> + 'a {
+        self.groups.iter()
+            .filter_map(move |(scheme, digests)| digests.get(&scheme.hash(item)?))
+            .map(String::as_str)
+    }
+
+    /// checks every item across the rayon thread pool
+    pub fn check_all(&self, items: &[Cell]) -> Vec<Match> {
+        items.par_iter()
+            .flat_map_iter(|item| self.check(item).map(move |hash| Match {
+                candidate: item.clone(),
+                hash: hash.to_string()
+            }))
+            .collect()
+    }
+
+    /// stops looking for `hash` once it's been found
+    pub fn remove(&mut self, hash: &str) {
+        for digests in self.groups.values_mut() {
+            digests.retain(|_, x| x != hash);
+        }
+        self.groups.retain(|_, digests| !digests.is_empty());
+    }
+}
+
+pub(crate) fn decode_hex(x: &str) -> Option<Vec<u8>> {
+    if x.len() % 2 != 0 || !x.is_ascii() {
+        return None
+    }
+
+    (0..x.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&x[idx..idx+2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Generator, RuleCell, Rulebook,
+        patterns::ConstPattern,
+        scheduler::WorkStealing
+    };
+
+    #[test]
+    fn behavior_crack() {
+        let first = |targets: &Targets, item: &[u8]| targets.check(item).next().map(str::to_string);
+
+        // "password" and "p4ssw0rd"
+        let md5 = Targets::new(Some(Algorithm::Md5), vec![
+            "5f4dcc3b5aa765d61d8327deb882cf99",
+            "2a9d119df47ff993b662a8ef36f9ea20",
+            "",
+        ]).unwrap();
+        assert_eq!(md5.len(), 2);
+        assert_eq!(first(&md5, b"password").as_deref(), Some("5f4dcc3b5aa765d61d8327deb882cf99"));
+        assert_eq!(first(&md5, b"Password"), None);
+
+        let ntlm = Targets::new(Some(Algorithm::Ntlm), vec!["8846F7EAEE8FB117AD06BDD830B7586C"]).unwrap();
+        assert!(first(&ntlm, b"password").is_some());
+
+        let sha1 = Targets::new(Some(Algorithm::Sha1), vec!["5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8"]).unwrap();
+        assert!(first(&sha1, b"password").is_some());
+
+        let sha256 = Targets::new(Some(Algorithm::Sha256), vec!["5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8"]).unwrap();
+        assert!(first(&sha256, b"password").is_some());
+
+        let setup = pwhash::bcrypt::BcryptSetup { cost: Some(4), ..Default::default() };
+        let bcrypt = pwhash::bcrypt::hash_with(setup, "p4ssword").unwrap();
+        let bcrypt = Targets::new(None, vec![bcrypt.as_str()]).unwrap();
+        assert!(first(&bcrypt, b"p4ssword").is_some());
+        assert!(first(&bcrypt, b"password").is_none());
+
+        assert!(Targets::new(Some(Algorithm::Sha1), vec!["5f4dcc3b5aa765d61d8327deb882cf99"]).is_err());
+        assert!(Targets::new(Some(Algorithm::Md5), vec!["not hex"]).is_err());
+        assert!(Targets::new(None, vec!["5f4dcc3b5aa765d61d8327deb882cf99"]).is_err());
+        assert!("md6".parse::<Algorithm>().is_err());
+    }
+
+    #[test]
+    fn salted_formats() {
+        let first = |targets: &Targets, item: &[u8]| targets.check(item).next().map(str::to_string);
+
+        let formatted = Targets::new(None, vec![
+            "$1$abc$LrFFhM37tyd5kSPHlTKRE/",
+            "$6$saltsalt$IGEKuieUDY0tb/pAdACKty9so4tE2l/i.PZ5sh8CoyeUFQQ3XWbIVq5eh55BANk.izxzQpWjnAvVyn4TLb9UR.",
+            "pbkdf2_sha256$1000$pepper$HuEXlZkLFy+xnWOdvsjTOiQwsruTa6Un3J9JfLkGkL0=",
+        ]).unwrap();
+        assert_eq!(formatted.salts(), 3);
+        assert_eq!(first(&formatted, b"p4ssword").as_deref(), Some("$1$abc$LrFFhM37tyd5kSPHlTKRE/"));
+        assert!(first(&formatted, b"p4ssw0rd").unwrap().starts_with("$6$saltsalt$"));
+        assert!(first(&formatted, b"p@ssword").unwrap().starts_with("pbkdf2_sha256$"));
+        assert_eq!(first(&formatted, b"password"), None);
+
+        let mut pairs = Targets::new(Some(Algorithm::Sha1), vec![
+            "grain:d7639684a4594e54f28504aa5f1419edcbef97c2",
+            "rock:faa8174dec0b01e0f0e53600859827b148d96c3b",
+            "rock:5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8",
+        ]).unwrap();
+        assert_eq!((pairs.len(), pairs.salts()), (3, 2));
+        assert_eq!(pairs.check(b"p@ssword").count(), 2);
+        assert_eq!(first(&pairs, b"password"), None);
+
+        pairs.remove("grain:d7639684a4594e54f28504aa5f1419edcbef97c2");
+        assert_eq!((pairs.len(), pairs.salts()), (2, 1));
+
+        assert!(Targets::new(None, vec!["$9$abc$def"]).is_err());
+        let split = format!("$2b$10${}é{}", "a".repeat(21), "b".repeat(30));
+        assert_eq!(split.len(), 60);
+        assert!(Targets::new(None, vec![split.as_str()]).is_err());
+        assert!(Targets::new(None, vec!["pbkdf2_sha256$many$pepper$abc"]).is_err());
+    }
+
+    #[test]
+    fn crack_generations() {
+        let rules: Rulebook = vec![
+            (b'a', RuleCell::from(&b"4@"[..])),
+            (b'o', RuleCell::from(&b"0"[..])),
+        ].into();
+
+        let targets = Targets::new(Some(Algorithm::Md5), vec![
+            "2a9d119df47ff993b662a8ef36f9ea20",
+        ]).unwrap();
+
+        let mut gen = Generator::new(rules, WorkStealing::new(2).unwrap());
+        gen.seed(&b"password"[..]);
+
+        let mut found = Vec::new();
+        for _ in 0..3 {
+            let mut items = Vec::new();
+            gen.emit(|x| items.push(x));
+            found.extend(targets.check_all(&items));
+            gen.new_generation::<ConstPattern>().unwrap();
+        }
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(gen.origin(&found[0].candidate), Some(&Cell::from(&b"password"[..])));
+    }
+}
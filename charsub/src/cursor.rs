@@ -1,13 +1,9 @@
 use crate::{
     Cell,
-    RuleCell,
-    patterns::RuleEntry,
     unit::Permutation,
     Rulebook
 };
 
-use hashbrown::HashMap;
-
 #[derive(Debug, PartialEq)]
 pub enum Output {
     Permute(Permutation),
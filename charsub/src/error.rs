@@ -0,0 +1,54 @@
+use crate::Conflict;
+
+use std::{fmt, io};
+
+/// What the library refuses, instead of panicking over it
+#[derive(Debug)]
+pub enum Error {
+    /// a rule not written as `from:to`
+    InvalidRule(String),
+    /// a rule turning the character into nothing
+    EmptyRule(u8),
+    /// more room asked for than can be addressed
+    CapacityOverflow,
+    /// settings which don't go together, see `builder::GeneratorBuilder::build`
+    InvalidConfig(String),
+    /// differing rules for a character under `Merge::Strict`
+    Conflict(Conflict),
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(x: io::Error) -> Error {
+        Error::Io(x)
+    }
+}
+
+impl From<Conflict> for Error {
+    fn from(x: Conflict) -> Error {
+        Error::Conflict(x)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Conflict(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidRule(x) => write!(f, "Expected rule as `from:to`, got '{}'", x),
+            Error::EmptyRule(from) => write!(f, "Expected rule for '{}' to turn it into something", std::ascii::escape_default(*from)),
+            Error::CapacityOverflow => write!(f, "Asked for more room than can be addressed"),
+            Error::InvalidConfig(x) => write!(f, "Invalid configuration, {}", x),
+            Error::Conflict(e) => write!(f, "{}", e),
+            Error::Io(e) => write!(f, "{}", e)
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    Cell,
+    Rulebook,
+    analyse::chains,
+    patterns::Handler,
+    unit::Permutation
+};
+
+use std::fmt;
+
+/// How a seed's character turns into the target's at one position
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// the seed already has it
+    Kept,
+    /// through the characters along the shortest chain of rules,
+    /// the seed's first and the target's last
+    Substituted(Vec<u8>),
+    /// rules lead there, but the handler never substitutes this position
+    Blocked(Vec<u8>),
+    /// no chain of rules leads there
+    Unreachable,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub idx: usize,
+    pub from: u8,
+    pub to: u8,
+    pub step: Step,
+}
+
+/// Whether and how a seed turns into a target
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub seed: Cell,
+    pub target: Cell,
+    /// the handler deciding which positions are substituted
+    pub handler: String,
+    /// empty if seed and target differ in length,
+    /// which substitutions never change
+    pub positions: Vec<Position>,
+}
+
+impl Explanation {
+    pub fn derivable(&self) -> bool {
+        self.seed.len() == self.target.len()
+            && self.positions.iter().all(|x| matches!(x.step, Step::Kept | Step::Substituted(_)))
+    }
+
+    /// the generation the target first appears in, the seed being generation 0.
+    /// each generation takes a single step along a chain at a single position
+    pub fn generation(&self) -> Option<usize> {
+        if !self.derivable() {
+            return None
+        }
+
+        Some(self.positions.iter()
+            .map(|x| match &x.step {
+                Step::Substituted(chain) => chain.len() - 1,
+                _ => 0
+            })
+            .sum())
+    }
+}
+
+/// explains how `seed` turns into `target` under `rules`, without generating anything
+pub fn explain<H>(seed: &[u8], target: &[u8], rules: &Rulebook) -> Explanation
+where H: Handler
+{
+    let seed = Cell::from(seed);
+    let target = Cell::from(target);
+
+    let mut positions = Vec::new();
+    if seed.len() == target.len() {
+        for (idx, (from, to)) in seed.iter().zip(target.iter()).enumerate() {
+            let step = match chains(*from, rules).remove(to) {
+                _ if from == to => Step::Kept,
+                None => Step::Unreachable,
+                Some(mut chain) => {
+                    chain.push(*to);
+                    let entry = rules.0[from].clone();
+                    match H::handle(&Permutation::new(seed.clone(), entry, idx)) {
+                        true => Step::Blocked(chain),
+                        false => Step::Substituted(chain)
+                    }
+                }
+            };
+            positions.push(Position { idx, from: *from, to: *to, step });
+        }
+    }
+
+    Explanation {
+        seed,
+        target,
+        handler: format!("{:?}", H::default()),
+        positions,
+    }
+}
+
+fn escape(bytes: &[u8]) -> String {
+    bytes.iter().flat_map(|x| std::ascii::escape_default(*x)).map(char::from).collect()
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}: ", escape(&self.seed), escape(&self.target))?;
+        match self.generation() {
+            Some(generation) => writeln!(f, "yes, in generation {}", generation)?,
+            None => writeln!(f, "no")?
+        }
+
+        if self.seed.len() != self.target.len() {
+            return writeln!(
+                f, "  lengths differ ({} and {}), substitutions never change a candidate's length",
+                self.seed.len(), self.target.len()
+            )
+        }
+
+        for position in &self.positions {
+            write!(f, "  {:>3}  {} -> {}  ", position.idx, escape(&[position.from]), escape(&[position.to]))?;
+            let rules = |chain: &[u8]| chain.windows(2)
+                .map(|x| format!("{}:{}", escape(&x[..1]), escape(&x[1..])))
+                .collect::<Vec<_>>()
+                .join(" then ");
+
+            match &position.step {
+                Step::Kept => writeln!(f, "kept")?,
+                Step::Substituted(chain) => writeln!(f, "{}", rules(chain))?,
+                Step::Blocked(chain) => writeln!(
+                    f, "{}, blocked by {} at index {}",
+                    rules(chain), self.handler, position.idx
+                )?,
+                Step::Unreachable => writeln!(f, "no rules lead there")?
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Generator,
+        patterns::{ConstPattern, ModulusPattern},
+        scheduler::SingleThread,
+        testutil::leet as rules
+    };
+
+    #[test]
+    fn behavior_explain() {
+        let found = explain::<ConstPattern>(b"password", b"PAss", &rules());
+        assert!(!found.derivable());
+        assert!(found.to_string().contains("lengths differ"));
+
+        let found = explain::<ConstPattern>(b"password", b"PAssw0rd", &rules());
+        assert_eq!(found.generation(), Some(4));
+        assert_eq!(found.positions[1].step, Step::Substituted(vec![b'a', b'4', b'A']));
+        assert_eq!(found.positions[2].step, Step::Kept);
+        assert!(found.to_string().contains("a:4 then 4:A"));
+
+        // modulo never substitutes the first position
+        let found = explain::<ModulusPattern>(b"password", b"P@ssw0rd", &rules());
+        assert!(!found.derivable());
+        assert_eq!(found.positions[0].step, Step::Blocked(vec![b'p', b'P']));
+        assert!(found.to_string().contains("blocked by ModulusPattern at index 0"));
+
+        let found = explain::<ConstPattern>(b"password", b"passw0rD", &rules());
+        assert_eq!(found.positions[7].step, Step::Unreachable);
+        assert_eq!(found.generation(), None);
+    }
+
+    #[test]
+    fn explain_generations() {
+        let mut gen = Generator::new(rules(), SingleThread::new());
+        gen.seed(&b"pass"[..]);
+
+        let mut first = Vec::new();
+        for generation in 0..8 {
+            gen.emit(|x| first.push((x, generation)));
+            gen.new_generation::<ModulusPattern>().unwrap();
+        }
+
+        assert!(!first.is_empty());
+        for (item, generation) in first {
+            let found = explain::<ModulusPattern>(b"pass", &item, &rules());
+            assert_eq!(found.generation(), Some(generation), "{:?}", item);
+        }
+        assert_eq!(explain::<ModulusPattern>(b"pass", b"Pass", &rules()).generation(), None);
+    }
+}
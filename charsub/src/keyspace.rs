@@ -0,0 +1,294 @@
+use crate::{
+    Cell,
+    Rulebook,
+    patterns::Handler,
+    unit::Permutation
+};
+
+use std::ops::Range;
+
+use crate::serial::serde_string;
+
+use hashbrown::HashSet;
+
+/// Every candidate a seed can become.
+///
+/// Each generation substitutes a single position, and positions
+/// never influence each other, so the candidates reachable from a seed
+/// are every combination of the characters reachable at each position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyspace {
+    seed: Cell,
+    /// characters reachable per position, the seed's own first
+    positions: Vec<Vec<u8>>,
+}
+
+impl Keyspace {
+    pub fn new<H: Handler>(seed: &Cell, rules: &Rulebook) -> Self {
+        Self::build(seed, rules, H::handle, reach)
+    }
+
+    /// the candidates of `Closure::SingleHop`, where only
+    /// the seed's own characters are substituted
+    pub fn single_hop<H: Handler>(seed: &Cell, rules: &Rulebook) -> Self {
+        Self::build(seed, rules, H::handle, hop)
+    }
+
+    pub(crate) fn build(
+        seed: &Cell,
+        rules: &Rulebook,
+        handle: fn(&Permutation) -> bool,
+        reach: fn(u8, &Rulebook) -> Vec<u8>
+    ) -> Self {
+        let positions = seed.iter()
+            .enumerate()
+            .map(|(idx, byte)| match rules.0.get(byte) {
+                Some(entry) if !handle(&Permutation::new(seed.clone(), entry.clone(), idx)) => reach(*byte, rules),
+                _ => vec![*byte]
+            })
+            .collect();
+
+        Self {
+            seed: seed.clone(),
+            positions
+        }
+    }
+
+    pub fn seed(&self) -> &Cell {
+        &self.seed
+    }
+
+    /// characters which can end up at `idx`, starting with the seed's
+    pub fn reach(&self, idx: usize) -> &[u8] {
+        &self.positions[idx]
+    }
+
+    /// amount of candidates, seed included.
+    /// saturates rather than overflowing
+    pub fn size(&self) -> u128 {
+        self.positions.iter()
+            .fold(1u128, |size, reach| size.saturating_mul(reach.len() as u128))
+    }
+
+    /// the candidate at `index`, counting like an odometer
+    /// whose last position turns fastest. index 0 is the seed
+    pub fn get(&self, mut index: u128) -> Option<Cell> {
+        if index >= self.size() {
+            return None
+        }
+
+        let mut item = self.seed.clone();
+        for (idx, reach) in self.positions.iter().enumerate().rev() {
+            let radix = reach.len() as u128;
+            item[idx] = reach[(index % radix) as usize];
+            index /= radix;
+        }
+        Some(item)
+    }
+
+    /// inverse of `get`, `None` if `item` isn't in the keyspace
+    pub fn index_of(&self, item: &[u8]) -> Option<u128> {
+        if item.len() != self.positions.len() {
+            return None
+        }
+
+        let mut index: u128 = 0;
+        for (byte, reach) in item.iter().zip(&self.positions) {
+            let digit = reach.iter().position(|x| x == byte)?;
+            index = index.saturating_mul(reach.len() as u128).saturating_add(digit as u128);
+        }
+        Some(index)
+    }
+}
+
+/// One of `count` disjoint slices which together cover every keyspace,
+/// written `index/count` counting from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    index: usize,
+    count: usize,
+}
+
+impl Shard {
+    /// `None` unless `1 <= index <= count`
+    pub fn new(index: usize, count: usize) -> Option<Self> {
+        if index == 0 || index > count {
+            return None
+        }
+        Some(Self { index, count })
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// lays the keyspaces of `sizes` end to end and takes this
+    /// shard's even share, as ranges of indices per keyspace
+    pub fn ranges(&self, sizes: &[u128]) -> Vec<(usize, Range<u128>)> {
+        let total = sizes.iter().fold(0, |x: u128, y| x.saturating_add(*y));
+        let (count, idx) = (self.count as u128, self.index as u128 - 1);
+        let (share, rest) = (total / count, total % count);
+
+        let start = idx * share + idx.min(rest);
+        let end = start + share + (idx < rest) as u128;
+
+        let mut ranges = Vec::new();
+        let mut offset: u128 = 0;
+        for (seed, size) in sizes.iter().enumerate() {
+            let (from, to) = (start.max(offset), end.min(offset.saturating_add(*size)));
+            if from < to {
+                ranges.push((seed, from - offset..to - offset));
+            }
+            offset = offset.saturating_add(*size);
+        }
+        ranges
+    }
+}
+
+impl std::fmt::Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+serde_string!(Shard, |x| x.to_string(), str::parse);
+
+impl std::str::FromStr for Shard {
+    type Err = String;
+
+    fn from_str(x: &str) -> Result<Self, String> {
+        let bad = || format!("Expected shard as `index/count`, got '{}'", x);
+        let (index, count) = x.split_once('/').ok_or_else(bad)?;
+
+        Shard::new(
+            index.trim().parse().map_err(|_| bad())?,
+            count.trim().parse().map_err(|_| bad())?
+        ).ok_or_else(bad)
+    }
+}
+
+/// characters `byte` turns into through any chain of rules, itself first
+pub(crate) fn reach(byte: u8, rules: &Rulebook) -> Vec<u8> {
+    let mut seen = HashSet::new();
+    let mut order = vec![byte];
+    seen.insert(byte);
+
+    let mut idx = 0;
+    while let Some(from) = order.get(idx).copied() {
+        if let Some(entry) = rules.0.get(&from) {
+            for to in crate::RuleCell::from(entry.clone()) {
+                if seen.insert(to) {
+                    order.push(to);
+                }
+            }
+        }
+        idx += 1;
+    }
+    order
+}
+
+/// characters a single rule turns `byte` into, itself first
+pub(crate) fn hop(byte: u8, rules: &Rulebook) -> Vec<u8> {
+    let mut order = vec![byte];
+    if let Some(entry) = rules.0.get(&byte) {
+        for to in crate::RuleCell::from(entry.clone()) {
+            if !order.contains(&to) {
+                order.push(to);
+            }
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Generator, Length, RuleCell,
+        patterns::{ConstPattern, ModulusPattern},
+        scheduler::{Scheduler, SingleThread}
+    };
+
+    #[test]
+    fn behavior_keyspace() {
+        let rules: Rulebook = vec![
+            (b'a', RuleCell::from(&b"4@"[..])),
+            (b'4', RuleCell::from(&b"A"[..])),
+            (b's', RuleCell::from(&b"5"[..])),
+        ].into();
+        let seed = Cell::from(&b"sass"[..]);
+
+        let keyspace = Keyspace::new::<ConstPattern>(&seed, &rules);
+        assert_eq!(keyspace.reach(1), b"a4@A");
+        assert_eq!(keyspace.size(), 2 * 4 * 2 * 2);
+        assert_eq!(Keyspace::new::<ModulusPattern>(&seed, &rules).size(), 4 * 2 * 2);
+
+        let mut single = SingleThread::new();
+        single.push(seed);
+        for _ in 0..8 {
+            single.new_generation::<ConstPattern>(&rules);
+        }
+        assert_eq!(single.length() as u128, keyspace.size());
+
+        let found: HashSet<Cell> = single.into();
+        for index in 0..keyspace.size() {
+            let item = keyspace.get(index).unwrap();
+            assert!(found.contains(&item));
+            assert_eq!(keyspace.index_of(&item), Some(index));
+        }
+        assert_eq!(keyspace.get(keyspace.size()), None);
+    }
+
+    #[test]
+    fn shards_cover_once() {
+        let sizes = [7, 0, 12, 1];
+        for count in 1..=9 {
+            let mut covered = Vec::new();
+            for index in 1..=count {
+                let shard: Shard = format!("{}/{}", index, count).parse().unwrap();
+                for (seed, range) in shard.ranges(&sizes) {
+                    covered.extend(range.map(|idx| (seed, idx)));
+                }
+            }
+
+            let expected: Vec<_> = sizes.iter()
+                .enumerate()
+                .flat_map(|(seed, size)| (0..*size).map(move |idx| (seed, idx)))
+                .collect();
+            assert_eq!(covered, expected);
+        }
+        assert!("0/3".parse::<Shard>().is_err());
+        assert!("4/3".parse::<Shard>().is_err());
+    }
+
+    #[test]
+    fn generator_shards() {
+        let rules: Rulebook = vec![
+            (b'o', RuleCell::from(&b"0"[..])),
+            (b'b', RuleCell::from(&b"8B"[..])),
+        ].into();
+
+        let mut whole = Generator::new(rules.clone(), SingleThread::new());
+        whole.seed(&b"bob"[..]);
+        whole.seed(&b"boo"[..]);
+        for _ in 0..4 {
+            whole.new_generation::<ConstPattern>().unwrap();
+        }
+        let expected: HashSet<Cell> = whole.into_set();
+
+        let mut found = Vec::new();
+        for index in 1..=4 {
+            let mut gen = Generator::new(rules.clone(), SingleThread::new());
+            gen.seed(&b"bob"[..]);
+            gen.seed(&b"boo"[..]);
+            found.extend(gen.shard::<ConstPattern>(Shard::new(index, 4).unwrap()));
+        }
+        assert_eq!(found.len(), expected.len());
+        assert_eq!(found.into_iter().collect::<HashSet<_>>(), expected);
+        assert!("4/3".parse::<Shard>().is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    RuleCell,
+    Rulebook
+};
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write}
+};
+
+use hashbrown::HashMap;
+
+/// How often a substitution was seen, and where
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Observed {
+    pub count: u64,
+    /// times it was seen per position, 0 being the first character
+    pub positions: BTreeMap<usize, u64>,
+}
+
+/// Substitutions people made of base words,
+/// learned from pairs of a word and its variant
+#[derive(Debug, Clone, Default)]
+pub struct Learner {
+    seen: HashMap<(u8, u8), Observed>,
+    pairs: u64,
+    skipped: u64,
+}
+
+impl Learner {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// aligns `variant` with `base` character by character, pairs
+    /// which differ in length can't be told apart by substitutions
+    /// and are skipped. returns whether the pair was used
+    pub fn observe(&mut self, base: &[u8], variant: &[u8]) -> bool {
+        if base.len() != variant.len() {
+            self.skipped += 1;
+            return false
+        }
+
+        for (idx, (from, to)) in base.iter().zip(variant).enumerate() {
+            if from == to {
+                continue
+            }
+            let observed = self.seen.entry((*from, *to)).or_default();
+            observed.count += 1;
+            *observed.positions.entry(idx).or_default() += 1;
+        }
+        self.pairs += 1;
+        true
+    }
+
+    /// pairs used
+    pub fn pairs(&self) -> u64 {
+        self.pairs
+    }
+
+    /// pairs skipped for differing in length
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// every substitution seen at least `min_count` times, by the character
+    /// replaced, then most often seen first
+    pub fn substitutions(&self, min_count: u64) -> Vec<(u8, u8, &Observed)> {
+        let mut found: Vec<(u8, u8, &Observed)> = self.seen.iter()
+            .filter(|(_, observed)| observed.count >= min_count)
+            .map(|((from, to), observed)| (*from, *to, observed))
+            .collect();
+        found.sort_unstable_by(|x, y| x.0.cmp(&y.0)
+            .then(y.2.count.cmp(&x.2.count))
+            .then(x.1.cmp(&y.1)));
+        found
+    }
+
+    /// rules for every substitution seen at least `min_count` times,
+    /// each character's likeliest replacement first
+    pub fn rulebook(&self, min_count: u64) -> Rulebook {
+        self.rules(min_count).into()
+    }
+
+    fn rules(&self, min_count: u64) -> Vec<(u8, RuleCell)> {
+        let mut rules: Vec<(u8, RuleCell)> = Vec::new();
+        for (from, to, _) in self.substitutions(min_count) {
+            match rules.last_mut() {
+                Some((last, entry)) if *last == from => entry.push(to),
+                _ => rules.push((from, RuleCell::from(&[to][..])))
+            }
+        }
+        rules
+    }
+
+    /// writes a rule file `Rulebook::load` reads, with how often
+    /// and where each substitution was seen in comments. the rules
+    /// keep only the order of each character's replacements, not the counts
+    pub fn write<W>(&self, mut out: W, min_count: u64) -> io::Result<()>
+    where W: Write
+    {
+        writeln!(out, "# learned from {} pairs, {} skipped for differing in length", self.pairs, self.skipped)?;
+        writeln!(out, "# replacements are in order of how often they were seen, the counts aren't loaded")?;
+
+        let substitutions = self.substitutions(min_count);
+        for (from, entry) in self.rules(min_count) {
+            writeln!(out)?;
+            for (_, to, observed) in substitutions.iter().filter(|x| x.0 == from) {
+                let mut positions: Vec<(&usize, &u64)> = observed.positions.iter().collect();
+                positions.sort_by(|x, y| y.1.cmp(x.1).then(x.0.cmp(y.0)));
+
+                let positions: Vec<String> = positions.iter()
+                    .take(5)
+                    .map(|(idx, count)| format!("{} ({})", idx, count))
+                    .collect();
+                writeln!(
+                    out, "# {} -> {} seen {} times, at {}",
+                    escape(from), escape(*to), observed.count, positions.join(", ")
+                )?;
+            }
+
+            writeln!(out, "{}", Rulebook::format_rule(from, &entry))?;
+        }
+        Ok(())
+    }
+}
+
+fn escape(byte: u8) -> String {
+    std::ascii::escape_default(byte).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::patterns::RuleEntry;
+
+    #[test]
+    fn behavior_learn() {
+        let mut learner = Learner::new();
+        for (base, variant) in [
+            ("password", "p4ssword"),
+            ("password", "p@ssw0rd"),
+            ("sunshine", "5un5h1ne"),
+            ("letmein", "l3tm31n"),
+            ("monkey", "m0nkey"),
+            ("dragon", "dr4g0n!"),
+        ] {
+            learner.observe(base.as_bytes(), variant.as_bytes());
+        }
+        assert_eq!((learner.pairs(), learner.skipped()), (5, 1));
+
+        let rules = learner.rulebook(1);
+        assert_eq!(rules.0[&b'a'], RuleEntry::Multi(RuleCell::from(&b"4@"[..])));
+        assert_eq!(rules.0[&b'o'], RuleEntry::Single(b'0'));
+        assert_eq!(learner.rulebook(2).0.len(), 4);
+
+        let mut written = Vec::new();
+        learner.write(&mut written, 1).unwrap();
+        let read: Rulebook = String::from_utf8(written).unwrap().parse().unwrap();
+        assert_eq!(read.fingerprint(), rules.fingerprint());
+
+        let (_, _, observed) = learner.substitutions(1).into_iter()
+            .find(|x| (x.0, x.1) == (b's', b'5'))
+            .unwrap();
+        assert_eq!(observed.count, 2);
+        assert_eq!(observed.positions.iter().collect::<Vec<_>>(), vec![(&0, &1), (&3, &1)]);
+    }
+
+    #[test]
+    fn writes_loadable_rules() {
+        let mut learner = Learner::new();
+        learner.observe(b"#:\\e\xe9", b":#\xe9\n\\");
+
+        let mut written = Vec::new();
+        learner.write(&mut written, 1).unwrap();
+        let read: Rulebook = String::from_utf8(written).unwrap().parse().unwrap();
+        assert_eq!(read.fingerprint(), learner.rulebook(1).fingerprint());
+        assert_eq!(read.0[&b'#'], RuleEntry::Single(b':'));
+        assert_eq!(read.0[&0xe9], RuleEntry::Single(b'\\'));
+        assert_eq!(read.0.len(), 5);
+    }
+
+    #[test]
+    fn rule_files() {
+        let rules: Rulebook = "# comment\na:4@\n\n : _\ns:5\r\n".parse().unwrap();
+        assert_eq!(rules.0[&b' '], RuleEntry::Multi(RuleCell::from(&b" _"[..])));
+        assert_eq!(rules.0[&b's'], RuleEntry::Single(b'5'));
+        assert!("a4".parse::<Rulebook>().is_err());
+        assert!("a:".parse::<Rulebook>().is_err());
+
+        let rules: Rulebook = "\\x23:\\x41\\xZ!\n\\:/\n\\x5c:\\".parse().unwrap();
+        assert_eq!(rules.0[&b'#'], RuleEntry::Multi(RuleCell::from(&b"A\\xZ!"[..])));
+        assert_eq!(rules.0[&b'\\'], RuleEntry::Multi(RuleCell::from(&b"/\\"[..])));
+        assert_eq!(Rulebook::format_rule(b'#', b"\\\xff:"), "\\x23:\\x5c\\xff:");
+    }
+}
@@ -5,10 +5,36 @@ mod patterns;
 mod unit;
 mod cursor;
 mod api;
+mod shard;
+mod spill;
+mod bloom;
+mod serial;
+mod error;
 pub mod scheduler;
+pub mod storage;
+pub mod stream;
+pub mod checkpoint;
+pub mod keyspace;
+pub mod progress;
+pub mod cancel;
+pub mod order;
+pub mod remote;
+pub mod crack;
+pub mod policy;
+pub mod markov;
+pub mod learn;
+pub mod analyse;
+pub mod explain;
+pub mod closure;
+pub mod builder;
+#[cfg(test)]
+mod testutil;
 
 pub use scheduler::Scheduler;
 pub use unit::Permutation;
+pub use cancel::CancelToken;
+pub use order::Order;
+pub use error::Error;
 pub use api::*;
 pub use patterns::*;
 
@@ -0,0 +1,230 @@
+use crate::{
+    patterns::Filter,
+    crack::decode_hex
+};
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Arc
+};
+
+use hashbrown::HashMap;
+
+const MAGIC: &str = "charsub markov 1";
+
+/// pads the context before a word's first character
+const START: u8 = 0;
+/// follows a word's last character
+const END: u8 = b'\n';
+
+/// Character level n-gram model of how likely a word is.
+///
+/// Every character is predicted from the `order` characters before it,
+/// with add-one smoothing so unseen transitions aren't impossible.
+/// On disk this is a header, then `context next count` per line
+/// with the context and next character hex encoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+    order: usize,
+    /// how often each character followed a context
+    counts: HashMap<Vec<u8>, HashMap<u8, u32>>,
+    /// how often each context was seen at all
+    totals: HashMap<Vec<u8>, u32>,
+}
+
+impl Model {
+    pub fn new(order: usize) -> Self {
+        Self {
+            order,
+            counts: HashMap::new(),
+            totals: HashMap::new(),
+        }
+    }
+
+    /// model of every word in `corpus`
+    pub fn train<I, B>(order: usize, corpus: I) -> Self
+    where
+        I: IntoIterator<Item=B>,
+        B: AsRef<[u8]>
+    {
+        let mut model = Self::new(order);
+        for word in corpus {
+            model.learn(word.as_ref());
+        }
+        model
+    }
+
+    pub fn learn(&mut self, word: &[u8]) {
+        for (context, next) in self.transitions(word) {
+            *self.counts.entry(context.clone()).or_default().entry(next).or_default() += 1;
+            *self.totals.entry(context).or_default() += 1;
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// mean log2 probability per character, end of word included.
+    /// closer to 0 is likelier, and words of any length compare
+    pub fn score(&self, word: &[u8]) -> f64 {
+        let transitions = self.transitions(word);
+        let total: f64 = transitions.iter()
+            .map(|(context, next)| {
+                let seen = self.counts.get(context)
+                    .and_then(|x| x.get(next))
+                    .copied()
+                    .unwrap_or(0);
+                let total = self.totals.get(context).copied().unwrap_or(0);
+                ((seen as f64 + 1.0) / (total as f64 + 256.0)).log2()
+            })
+            .sum();
+        total / transitions.len() as f64
+    }
+
+    /// every character of `word`, and its end, after what came before them
+    fn transitions(&self, word: &[u8]) -> Vec<(Vec<u8>, u8)> {
+        let mut padded = vec![START; self.order];
+        padded.extend_from_slice(word);
+        padded.push(END);
+
+        padded.windows(self.order + 1)
+            .map(|x| (x[..self.order].to_vec(), x[self.order]))
+            .collect()
+    }
+
+    pub fn save<P>(&self, path: P) -> io::Result<()>
+    where P: AsRef<Path>
+    {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{}", MAGIC)?;
+        writeln!(out, "order {}", self.order)?;
+        writeln!(out)?;
+
+        // sorted, so the same corpus always saves the same file
+        let mut lines: Vec<(&Vec<u8>, u8, u32)> = self.counts.iter()
+            .flat_map(|(context, next)| next.iter().map(move |(next, count)| (context, *next, *count)))
+            .collect();
+        lines.sort_unstable();
+
+        for (context, next, count) in lines {
+            writeln!(out, "{} {:02x} {}", encode_hex(context), next, count)?;
+        }
+        out.flush()
+    }
+
+    pub fn load<P>(path: P) -> io::Result<Self>
+    where P: AsRef<Path>
+    {
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines();
+        if lines.next() != Some(MAGIC) {
+            return Err(invalid("not a charsub markov model"))
+        }
+
+        let order = lines.next()
+            .and_then(|x| x.strip_prefix("order "))
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(|| invalid("markov model is missing its order"))?;
+        let mut model = Self::new(order);
+
+        for line in lines.filter(|x| !x.is_empty()) {
+            let mut fields = line.split(' ');
+            let (context, next, count) = (fields.next(), fields.next(), fields.next());
+
+            let context = context.and_then(decode_hex).filter(|x| x.len() == order);
+            let next = next.and_then(decode_hex).filter(|x| x.len() == 1);
+            let count: Option<u32> = count.and_then(|x| x.parse().ok());
+
+            match (context, next, count) {
+                (Some(context), Some(next), Some(count)) => {
+                    *model.counts.entry(context.clone()).or_default().entry(next[0]).or_default() += count;
+                    *model.totals.entry(context).or_default() += count;
+                },
+                _ => return Err(invalid("malformed markov model line"))
+            }
+        }
+        Ok(model)
+    }
+}
+
+/// Drops candidates the model scores below `min`
+#[derive(Debug, Clone)]
+pub struct Threshold {
+    pub model: Arc<Model>,
+    pub min: f64,
+}
+
+impl Filter for Threshold {
+    fn keep(&self, item: &[u8]) -> bool {
+        self.model.score(item) >= self.min
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn encode_hex(x: &[u8]) -> String {
+    x.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Generator, Order, RuleCell, Rulebook,
+        patterns::ConstPattern,
+        scheduler::SingleThread
+    };
+
+    fn corpus() -> Vec<&'static str> {
+        vec!["password", "password1", "passw0rd", "p4ssword", "pass1234", "letmein", "sunshine"]
+    }
+
+    #[test]
+    fn behavior_markov() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model");
+
+        let model = Model::train(2, corpus());
+        assert!(model.score(b"password") > model.score(b"p@$$w0rd"));
+        assert!(model.score(b"passw0rd") > model.score(b"qzxvjkwy"));
+
+        model.save(&path).unwrap();
+        assert_eq!(Model::load(&path).unwrap(), model);
+        assert!(Model::load(dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn likelihood_order() {
+        let rules: Rulebook = vec![
+            (b'a', RuleCell::from(&b"4@"[..])),
+            (b's', RuleCell::from(&b"5$"[..])),
+            (b'o', RuleCell::from(&b"0"[..])),
+        ].into();
+        let model = Arc::new(Model::train(2, corpus()));
+
+        let mut gen = Generator::new(rules, SingleThread::new());
+        gen.set_order(Order::Likelihood);
+        gen.set_model(model.clone());
+        gen.add_filter(Threshold { model: model.clone(), min: model.score(b"p4$sw0rd") });
+        gen.seed(&b"password"[..]);
+
+        let mut found = Vec::new();
+        for _ in 0..8 {
+            gen.emit(|x| found.push(x));
+            gen.new_generation::<ConstPattern>().unwrap();
+        }
+        gen.emit(|x| found.push(x));
+
+        assert_eq!(found.first().map(|x| x.as_slice()), Some(&b"password"[..]));
+        assert!(found.iter().any(|x| x.as_slice() == b"p4$sw0rd"));
+        assert!(found.len() < 3 * 3 * 3 * 2);
+
+        let scores: Vec<f64> = found.iter().map(|x| model.score(x)).collect();
+        assert!(scores.windows(2).all(|x| x[0] >= x[1]));
+    }
+}
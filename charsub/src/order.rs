@@ -0,0 +1,68 @@
+use serde::{Serialize, Deserialize};
+
+/// Order a `Generator` emits candidates in.
+///
+/// Every order but `Unordered` comes out the same
+/// regardless of scheduler, storage or thread count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    /// whichever order the scheduler hands them out in
+    #[default]
+    Unordered,
+    /// by how many substitutions they're apart from their seed,
+    /// then lexicographically. Each generation is sorted on its own
+    Substitutions,
+    /// byte-wise, held back until the run is exhausted
+    Lexicographic,
+    /// by their seed, then their index in the seed's `Keyspace`,
+    /// held back until the run is exhausted
+    Index,
+    /// likeliest first by the generator's `Model`, then byte-wise,
+    /// held back until the run is exhausted
+    Likelihood,
+}
+
+impl Order {
+    /// whether candidates are only emitted once the run is exhausted,
+    /// such runs can't be resumed from a checkpoint
+    pub fn is_buffered(&self) -> bool {
+        matches!(self, Order::Lexicographic | Order::Index | Order::Likelihood)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Cell, Generator,
+        keyspace::Keyspace,
+        patterns::ModulusPattern,
+        scheduler::{Scheduler, SingleThread, WorkStealing},
+        testutil::{generations, leet as rules},
+        Snapshot
+    };
+
+    fn run<T: Scheduler + Snapshot + crate::Length>(scheduler: T, order: Order) -> Vec<Cell> {
+        let mut gen = Generator::new(rules(), scheduler);
+        gen.set_order(order);
+        gen.seed(&b"password"[..]);
+        generations::<ModulusPattern, _>(&mut gen, 6)
+    }
+
+    #[test]
+    fn behavior_order() {
+        for order in [Order::Substitutions, Order::Lexicographic, Order::Index] {
+            assert_eq!(run(SingleThread::new(), order), run(WorkStealing::new(4).unwrap(), order));
+        }
+
+        let keyspace = Keyspace::new::<ModulusPattern>(&Cell::from(&b"password"[..]), &rules());
+        let by_index: Vec<Cell> = (0..keyspace.size()).filter_map(|x| keyspace.get(x)).collect();
+        assert_eq!(run(SingleThread::new(), Order::Index), by_index);
+
+        let mut sorted = by_index;
+        sorted.sort();
+        assert_eq!(run(SingleThread::new(), Order::Lexicographic), sorted);
+    }
+}
@@ -1,11 +1,11 @@
 use crate::{
     RuleCell,
-    unit::Permutation
+    unit::Permutation,
+    serial::serde_string
 };
 
 pub trait Handler
 where Self: std::fmt::Debug + Default {
-    #[inline]
     fn handle(permute: &Permutation) -> bool;
 }
 
@@ -22,6 +22,10 @@ impl RuleEntry {
             Self::Multi(buf) => buf.len() 
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 use smallvec::SmallVec;
@@ -53,3 +57,120 @@ impl Handler for ModulusPattern {
         permute.index() % permute.len() == 0
     }
 }
+
+/// A `Handler` chosen at runtime, by the name
+/// checkpoints and workers know it under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pattern {
+    /// `ModulusPattern`
+    #[default]
+    Modulo,
+    /// `ConstPattern`
+    Const,
+}
+
+impl Pattern {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pattern::Const => "const",
+            Pattern::Modulo => "modulo",
+        }
+    }
+}
+
+impl std::str::FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(x: &str) -> Result<Self, String> {
+        Ok(match x.to_ascii_lowercase().as_str() {
+            "const" => Pattern::Const,
+            "modulo" => Pattern::Modulo,
+            _ => return Err(format!("Expected pattern, got '{}'", x))
+        })
+    }
+}
+
+/// Decides which candidates are emitted, where `Handler`
+/// decides which are permutated. Unlike handlers
+/// filters are values, so they can be configured at runtime
+pub trait Filter
+where Self: std::fmt::Debug + Send + Sync {
+    fn keep(&self, item: &[u8]) -> bool;
+}
+
+/// Gives up on candidates before they're permutated, where
+/// filters only keep candidates from being emitted
+pub trait Prune
+where Self: std::fmt::Debug + Send + Sync {
+    /// whether anything worth emitting could still come of `item`,
+    /// `handle` deciding which positions are permutated like `Handler::handle`
+    fn viable(&self, item: &[u8], rules: &crate::Rulebook, handle: fn(&Permutation) -> bool) -> bool;
+}
+
+/// Every one of them has to find a candidate viable
+impl Prune for Vec<std::sync::Arc<dyn Prune>> {
+    fn viable(&self, item: &[u8], rules: &crate::Rulebook, handle: fn(&Permutation) -> bool) -> bool {
+        self.iter().all(|x| x.viable(item, rules, handle))
+    }
+}
+
+/// Keeps candidates the expression matches somewhere in
+#[derive(Debug, Clone)]
+pub struct Include(pub regex::bytes::Regex);
+impl Filter for Include {
+    fn keep(&self, item: &[u8]) -> bool {
+        self.0.is_match(item)
+    }
+}
+
+serde_string!(Include, |x| x.0.as_str().to_string(), |x| regex(x).map(Include));
+
+/// Drops candidates the expression matches somewhere in
+#[derive(Debug, Clone)]
+pub struct Exclude(pub regex::bytes::Regex);
+impl Filter for Exclude {
+    fn keep(&self, item: &[u8]) -> bool {
+        !self.0.is_match(item)
+    }
+}
+
+serde_string!(Exclude, |x| x.0.as_str().to_string(), |x| regex(x).map(Exclude));
+
+fn regex(x: &str) -> Result<regex::bytes::Regex, String> {
+    regex::bytes::Regex::new(x).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Generator, Rulebook,
+        scheduler::SingleThread
+    };
+    use regex::bytes::Regex;
+
+    #[test]
+    fn behavior_filters() {
+        let rules: Rulebook = vec![
+            (b'a', RuleCell::from(&b"4@"[..])),
+            (b's', RuleCell::from(&b"5$"[..])),
+        ].into();
+
+        let mut gen = Generator::new(rules, SingleThread::new());
+        gen.add_filter(Include(Regex::new("[0-9]$").unwrap()));
+        gen.add_filter(Exclude(Regex::new("[^a-z0-9]{2}").unwrap()));
+        gen.seed(&b"pass"[..]);
+
+        let mut found = Vec::new();
+        for _ in 0..4 {
+            gen.emit(|x| found.push(x));
+            gen.new_generation::<ConstPattern>().unwrap();
+        }
+        gen.emit(|x| found.push(x));
+
+        found.sort();
+        let expected: Vec<&[u8]> = vec![b"p4$5", b"p455", b"p4s5", b"p@55", b"p@s5", b"pa$5", b"pa55", b"pas5"];
+        assert_eq!(found.iter().map(|x| x.as_slice()).collect::<Vec<_>>(), expected);
+    }
+}
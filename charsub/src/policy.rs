@@ -0,0 +1,294 @@
+use crate::{
+    Rulebook,
+    patterns::{Filter, Handler, Prune},
+    unit::Permutation
+};
+
+use std::{
+    fs,
+    io,
+    path::Path
+};
+
+use serde::{Serialize, Deserialize};
+
+/// Kinds of characters a policy can require
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Class {
+    Lower,
+    Upper,
+    Digit,
+    /// anything that isn't a letter or a digit
+    Symbol,
+}
+
+impl Class {
+    pub fn of(byte: u8) -> Self {
+        match byte {
+            b'a'..=b'z' => Class::Lower,
+            b'A'..=b'Z' => Class::Upper,
+            b'0'..=b'9' => Class::Digit,
+            _ => Class::Symbol,
+        }
+    }
+
+    fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+}
+
+impl std::str::FromStr for Class {
+    type Err = String;
+
+    fn from_str(x: &str) -> Result<Self, String> {
+        Ok(match x.trim().to_ascii_lowercase().as_str() {
+            "lower" => Class::Lower,
+            "upper" => Class::Upper,
+            "digit" => Class::Digit,
+            "symbol" => Class::Symbol,
+            _ => return Err(format!("Expected lower, upper, digit or symbol, got '{}'", x))
+        })
+    }
+}
+
+/// What a candidate must look like to be emitted.
+///
+/// Read from a file, one setting per line
+/// ```text
+/// length 8-64
+/// require digit
+/// require symbol
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    pub min_len: usize,
+    /// left out when unbounded, which few formats can hold
+    #[serde(skip_serializing_if = "unbounded")]
+    pub max_len: usize,
+    /// classes every candidate needs at least one character of
+    pub require: Vec<Class>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            min_len: 0,
+            max_len: usize::MAX,
+            require: Vec::new(),
+        }
+    }
+}
+
+fn unbounded(len: &usize) -> bool {
+    *len == usize::MAX
+}
+
+impl Policy {
+    pub fn load<P>(path: P) -> io::Result<Self>
+    where P: AsRef<Path>
+    {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn require(&mut self, class: Class) {
+        if !self.require.contains(&class) {
+            self.require.push(class);
+        }
+    }
+
+    pub fn allows(&self, item: &[u8]) -> bool {
+        let found = item.iter().fold(0, |mask, byte| mask | Class::of(*byte).bit());
+        (self.min_len..=self.max_len).contains(&item.len())
+            && self.required() & !found == 0
+    }
+
+    fn required(&self) -> u8 {
+        self.require.iter().fold(0, |mask, class| mask | class.bit())
+    }
+}
+
+impl Filter for Policy {
+    fn keep(&self, item: &[u8]) -> bool {
+        self.allows(item)
+    }
+}
+
+impl std::str::FromStr for Policy {
+    type Err = String;
+
+    fn from_str(x: &str) -> Result<Self, String> {
+        let mut policy = Policy::default();
+
+        for line in x.lines().map(str::trim).filter(|x| !x.is_empty() && !x.starts_with('#')) {
+            let bad = || format!("Expected `length MIN-MAX` or `require CLASS`, got '{}'", line);
+            let (key, value) = line.split_once(' ').ok_or_else(bad)?;
+            let value = value.trim();
+
+            match key {
+                "length" => {
+                    let (min, max) = value.split_once('-').ok_or_else(bad)?;
+                    policy.min_len = min.trim().parse().map_err(|_| bad())?;
+                    policy.max_len = match max.trim() {
+                        "" => usize::MAX,
+                        max => max.parse().map_err(|_| bad())?
+                    };
+                },
+                "require" => policy.require(value.parse()?),
+                _ => return Err(bad())
+            }
+        }
+        Ok(policy)
+    }
+}
+
+/// A policy along with the classes each character
+/// can still turn into under some rules, for
+/// giving up on candidates which can never satisfy it
+#[derive(Debug, Clone)]
+pub struct Pruner {
+    policy: Policy,
+    /// classes reachable from each byte, its own included
+    reach: Vec<u8>,
+}
+
+impl Pruner {
+    pub fn new(policy: Policy, rules: &Rulebook) -> Self {
+        let reach = (0..=255u8)
+            .map(|byte| crate::keyspace::reach(byte, rules)
+                .into_iter()
+                .fold(0, |mask, x| mask | Class::of(x).bit()))
+            .collect();
+
+        Self { policy, reach }
+    }
+
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    /// whether `item`, or anything it turns into, could satisfy the policy.
+    /// substitutions never change a candidate's length, and whatever
+    /// its descendants turn into it could turn into itself
+    pub fn viable<H>(&self, item: &[u8], rules: &Rulebook) -> bool
+    where H: Handler
+    {
+        Prune::viable(self, item, rules, H::handle)
+    }
+}
+
+impl Prune for Pruner {
+    fn viable(&self, item: &[u8], rules: &Rulebook, handle: fn(&Permutation) -> bool) -> bool {
+        if !(self.policy.min_len..=self.policy.max_len).contains(&item.len()) {
+            return false
+        }
+
+        let mut missing = self.policy.required();
+        for byte in item {
+            missing &= !Class::of(*byte).bit();
+        }
+
+        for (idx, byte) in item.iter().enumerate() {
+            if missing == 0 {
+                break
+            }
+            if self.reach[*byte as usize] & missing == 0 {
+                continue
+            }
+
+            // the handler may never let this position change
+            match rules.0.get(byte) {
+                Some(entry) if !handle(&Permutation::new(item.into(), entry.clone(), idx)) => {
+                    missing &= !self.reach[*byte as usize];
+                },
+                _ => {}
+            }
+        }
+        missing == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Cell, Generator,
+        patterns::{ConstPattern, ModulusPattern},
+        scheduler::{SingleThread, WorkStealing},
+        testutil::{generations, leet as rules}
+    };
+    use hashbrown::HashSet;
+
+    #[test]
+    fn behavior_policy() {
+        let policy: Policy = "# ours\nlength 8-64\nrequire digit\nrequire symbol\n".parse().unwrap();
+        assert_eq!(policy.require, vec![Class::Digit, Class::Symbol]);
+        assert!(policy.allows(b"p4$sword"));
+        assert!(!policy.allows(b"p4ssword"));
+        assert!(!policy.allows(b"p4$s"));
+        assert!("length 8".parse::<Policy>().is_err());
+        assert!("require emoji".parse::<Policy>().is_err());
+
+        let pruner = Pruner::new(policy, &rules());
+        assert!(pruner.viable::<ConstPattern>(b"password", &rules()));
+        assert!(pruner.viable::<ConstPattern>(b"p4$$word", &rules()));
+        // neither 'w', 'u', 'r' nor 'd' can ever become a digit
+        assert!(!pruner.viable::<ConstPattern>(b"p$$$wurd", &rules()));
+        assert!(!pruner.viable::<ConstPattern>(b"pass", &rules()));
+
+        // only the first position could gain an upper case letter, which modulo never substitutes
+        let mut upper = Policy::default();
+        upper.require(Class::Upper);
+        let pruner = Pruner::new(upper, &rules());
+        assert!(pruner.viable::<ConstPattern>(b"p$ssword", &rules()));
+        assert!(!pruner.viable::<ModulusPattern>(b"p$ssword", &rules()));
+    }
+
+    #[test]
+    fn policy_generations() {
+        let policy: Policy = "length 8-8\nrequire digit\nrequire symbol".parse().unwrap();
+
+        let mut whole = Generator::new(rules(), SingleThread::new());
+        whole.seed(&b"password"[..]);
+        for _ in 0..6 {
+            whole.new_generation::<ConstPattern>().unwrap();
+        }
+        let expected: HashSet<_> = whole.into_set()
+            .into_iter()
+            .filter(|x| policy.allows(x))
+            .collect();
+
+        let mut gen = Generator::new(rules(), WorkStealing::new(2).unwrap());
+        gen.set_policy(policy);
+        gen.seed(&b"password"[..]);
+        let found = generations::<ConstPattern, _>(&mut gen, 6);
+
+        assert_eq!(found.len(), expected.len());
+        assert_eq!(found.into_iter().collect::<HashSet<_>>(), expected);
+
+        // too short to ever satisfy the policy, so never permutated
+        let mut short = Generator::new(rules(), SingleThread::new());
+        short.set_policy(gen.policy().unwrap().clone());
+        short.seed(&b"pass"[..]);
+        short.new_generation::<ConstPattern>().unwrap();
+        assert_eq!(short.length(), 1);
+        assert_eq!(short.emit(|_| ()), 0);
+    }
+
+    #[test]
+    fn set_policy_replaces() {
+        let mut gen = Generator::new(rules(), SingleThread::new());
+        gen.set_policy("require digit".parse().unwrap());
+        gen.set_policy("length 8-8".parse().unwrap());
+        gen.seed(&b"password"[..]);
+
+        // the digit the first policy asked for no longer matters
+        let mut found = Vec::new();
+        gen.emit(|x| found.push(x));
+        assert_eq!(found, vec![Cell::from(&b"password"[..])]);
+        assert!(gen.filters.is_empty());
+    }
+}
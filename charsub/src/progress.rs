@@ -0,0 +1,83 @@
+use std::{
+    fmt,
+    time::Duration
+};
+
+/// How far a `Generator` has come, see `Generator::progress`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// candidates handed out by `emit`, including those
+    /// an earlier run handed out before being resumed
+    pub emitted: u128,
+    /// candidates which came from a resumed run
+    pub resumed: u128,
+    /// every candidate the seeds can become, once known.
+    /// seeds sharing candidates are counted twice,
+    /// so this is an upper bound when there are several
+    pub keyspace: Option<u128>,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// candidates emitted per second by this run
+    pub fn rate(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0
+        }
+        (self.emitted - self.resumed) as f64 / secs
+    }
+
+    /// share of the keyspace emitted, between 0 and 1
+    pub fn fraction(&self) -> Option<f64> {
+        self.keyspace
+            .filter(|size| *size > 0)
+            .map(|size| (self.emitted as f64 / size as f64).min(1.0))
+    }
+
+    /// time left at the current rate
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.rate();
+        let left = self.keyspace?.saturating_sub(self.emitted);
+        if rate <= 0.0 {
+            return None
+        }
+        Some(Duration::from_secs_f64(left as f64 / rate))
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} candidates, {:.0}/s", self.emitted, self.rate())?;
+
+        if let (Some(size), Some(fraction)) = (self.keyspace, self.fraction()) {
+            write!(f, ", {:.2}% of {}", fraction * 100.0, size)?;
+        }
+        if let Some(eta) = self.eta() {
+            let secs = eta.as_secs();
+            write!(f, ", eta {}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a single generation did, see `Generator::stats`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerationStats {
+    /// counting from 1, a resumed run carries on counting
+    pub generation: usize,
+    /// candidates the generation came up with, duplicates included
+    pub produced: usize,
+    /// those which were new
+    pub added: usize,
+    /// those which had been found before, or
+    /// which a bloom filter took for it
+    pub duplicates: usize,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for GenerationStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "generation {}: {} new, {} duplicates in {:.1?}", self.generation, self.added, self.duplicates, self.elapsed)
+    }
+}
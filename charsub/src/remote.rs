@@ -0,0 +1,685 @@
+/// Generation spread across worker processes.
+///
+/// The coordinator splits the keyspace of every seed into chunks of
+/// consecutive indices and hands them out one at a time, a worker
+/// enumerates its chunk and either sends the candidates back or writes
+/// them to a shard of its own. A chunk only counts once its worker
+/// reports it finished, so the chunk of a worker which disappears
+/// is simply handed to another.
+/// ---
+use crate::{
+    Cell, RuleCell, Rulebook,
+    closure::Closure,
+    keyspace::Keyspace,
+    patterns::{ConstPattern, ModulusPattern}
+};
+
+use std::{
+    collections::{HashMap, VecDeque, hash_map::Entry},
+    convert::{TryFrom, TryInto},
+    fs,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Condvar, Mutex},
+    thread,
+    time::Duration
+};
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+const HELLO: u8 = 1;
+const JOB: u8 = 2;
+const CHUNK: u8 = 3;
+const DONE: u8 = 4;
+const RESULTS: u8 = 5;
+const FINISHED: u8 = 6;
+
+/// longest body a frame may carry, a length past this
+/// is taken for a corrupt stream rather than allocated
+const MAX_FRAME: usize = 1 << 28;
+/// most candidates a chunk may hold, the count sent back with its results is a `u32`
+pub const MAX_CHUNK: u128 = u32::MAX as u128;
+
+/// What every worker is asked to generate
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    /// name of the handler, `const` or `modulo`
+    pub pattern: String,
+    /// how far the rules carry the seeds' characters
+    pub closure: Closure,
+    pub rules: Vec<(u8, RuleCell)>,
+    pub seeds: Vec<Cell>,
+}
+
+impl Job {
+    pub fn new<R>(pattern: &str, rules: &Rulebook, seeds: R) -> Self
+    where R: IntoIterator<Item=Cell>
+    {
+        let mut rules: Vec<(u8, RuleCell)> = rules.0.iter()
+            .map(|(key, entry)| (*key, RuleCell::from(entry.clone())))
+            .collect();
+        rules.sort();
+
+        Self {
+            pattern: pattern.to_string(),
+            closure: Closure::default(),
+            rules,
+            seeds: seeds.into_iter().collect()
+        }
+    }
+
+    /// keyspace of the seed at `seed`
+    pub fn keyspace(&self, seed: usize) -> io::Result<Keyspace> {
+        let mut rules: Rulebook = self.rules.clone().into();
+        let seed = self.seeds.get(seed).ok_or_else(|| invalid("no such seed"))?;
+        if self.closure == Closure::Transitive {
+            rules = rules.closure();
+        }
+
+        match (self.pattern.as_str(), self.closure) {
+            ("const", Closure::SingleHop) => Ok(Keyspace::single_hop::<ConstPattern>(seed, &rules)),
+            ("const", _) => Ok(Keyspace::new::<ConstPattern>(seed, &rules)),
+            ("modulo", Closure::SingleHop) => Ok(Keyspace::single_hop::<ModulusPattern>(seed, &rules)),
+            ("modulo", _) => Ok(Keyspace::new::<ModulusPattern>(seed, &rules)),
+            _ => Err(invalid("unknown pattern"))
+        }
+    }
+}
+
+/// Consecutive keyspace indices of a single seed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chunk {
+    pub id: u64,
+    pub seed: usize,
+    pub start: u128,
+    pub end: u128,
+}
+
+/// Where to listen or connect, either `unix:PATH`, `tcp://HOST:PORT` or `HOST:PORT`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Address {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl std::str::FromStr for Address {
+    type Err = io::Error;
+
+    fn from_str(x: &str) -> io::Result<Self> {
+        #[cfg(unix)]
+        if let Some(path) = x.strip_prefix("unix:") {
+            return Ok(Address::Unix(path.into()))
+        }
+        match x.strip_prefix("tcp://").unwrap_or(x) {
+            "" => Err(invalid("empty address")),
+            addr => Ok(Address::Tcp(addr.to_string()))
+        }
+    }
+}
+
+/// A connection to either end, blocking
+pub trait Conn: Read + Write + Send {
+    fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl Conn for TcpStream {
+    fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.set_read_timeout(timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Conn for UnixStream {
+    fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.set_read_timeout(timeout)
+    }
+}
+
+pub fn connect(addr: &Address) -> io::Result<Box<dyn Conn>> {
+    Ok(match addr {
+        Address::Tcp(addr) => Box::new(TcpStream::connect(addr)?),
+        #[cfg(unix)]
+        Address::Unix(path) => Box::new(UnixStream::connect(path)?),
+    })
+}
+
+/// Accepts workers for `serve`
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    pub fn bind(addr: &Address) -> io::Result<Self> {
+        let listener = match addr {
+            Address::Tcp(addr) => Listener::Tcp(TcpListener::bind(addr)?),
+            #[cfg(unix)]
+            Address::Unix(path) => Listener::Unix(UnixListener::bind(path)?, path.clone()),
+        };
+
+        match &listener {
+            Listener::Tcp(x) => x.set_nonblocking(true)?,
+            #[cfg(unix)]
+            Listener::Unix(x, _) => x.set_nonblocking(true)?,
+        }
+        Ok(listener)
+    }
+
+    /// where workers can connect to, resolving port 0
+    pub fn address(&self) -> io::Result<Address> {
+        Ok(match self {
+            Listener::Tcp(x) => Address::Tcp(x.local_addr()?.to_string()),
+            #[cfg(unix)]
+            Listener::Unix(_, path) => Address::Unix(path.clone()),
+        })
+    }
+
+    fn accept(&self) -> io::Result<Option<Box<dyn Conn>>> {
+        let conn: io::Result<Box<dyn Conn>> = match self {
+            Listener::Tcp(x) => x.accept().and_then(|(conn, _)| {
+                conn.set_nonblocking(false)?;
+                Ok(Box::new(conn) as Box<dyn Conn>)
+            }),
+            #[cfg(unix)]
+            Listener::Unix(x, _) => x.accept().and_then(|(conn, _)| {
+                conn.set_nonblocking(false)?;
+                Ok(Box::new(conn) as Box<dyn Conn>)
+            }),
+        };
+
+        match conn {
+            Ok(conn) => Ok(Some(conn)),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e)
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix(_, path) = self {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// How `serve` splits and hands out the job
+#[derive(Debug, Clone, Copy)]
+pub struct ServeOptions {
+    /// candidates per chunk, at most `MAX_CHUNK`
+    pub chunk: u128,
+    /// a worker silent for this long is given up on,
+    /// and its chunk handed to another
+    pub timeout: Option<Duration>,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self {
+            chunk: 1 << 16,
+            timeout: Some(Duration::from_secs(600))
+        }
+    }
+}
+
+/// What became of a `serve`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ServeStats {
+    pub chunks: usize,
+    /// chunks handed out again after their worker disappeared
+    pub reassigned: usize,
+    pub workers: usize,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    waiting: VecDeque<Chunk>,
+    outstanding: HashMap<u64, Chunk>,
+    stats: ServeStats,
+}
+
+impl Queue {
+    fn is_done(&self) -> bool {
+        self.waiting.is_empty() && self.outstanding.is_empty()
+    }
+}
+
+type Shared = Arc<(Mutex<Queue>, Condvar)>;
+
+/// Hands `job` out to whichever workers connect to `listener` until
+/// every chunk is finished. Candidates workers send back go to `sink`,
+/// those written by workers to their own shards don't.
+/// Seeds sharing candidates each produce them.
+pub fn serve<F>(listener: &Listener, job: &Job, options: ServeOptions, mut sink: F) -> io::Result<ServeStats>
+where F: FnMut(Cell)
+{
+    if options.chunk > MAX_CHUNK {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "chunk is larger than a worker can send back"))
+    }
+
+    let mut queue = Queue::default();
+    for seed in 0..job.seeds.len() {
+        let size = job.keyspace(seed)?.size();
+        let mut start = 0;
+        while start < size {
+            let end = size.min(start.saturating_add(options.chunk.max(1)));
+            queue.waiting.push_back(Chunk { id: queue.stats.chunks as u64, seed, start, end });
+            queue.stats.chunks += 1;
+            start = end;
+        }
+    }
+
+    let shared: Shared = Arc::new((Mutex::new(queue), Condvar::new()));
+    let (tx, rx) = mpsc::channel::<Vec<Cell>>();
+
+    loop {
+        while let Some(conn) = listener.accept()? {
+            let (shared, tx, job) = (shared.clone(), tx.clone(), job.clone());
+            shared.0.lock().unwrap().stats.workers += 1;
+            thread::spawn(move || {
+                let _ = coordinate(conn, &job, options, &shared, &tx);
+            });
+        }
+
+        match rx.recv_timeout(Duration::from_millis(10)) {
+            Ok(items) => items.into_iter().for_each(&mut sink),
+            Err(_) => {
+                if shared.0.lock().unwrap().is_done() {
+                    break
+                }
+            }
+        }
+    }
+
+    // results sent just before the last chunk finished
+    for items in rx.try_iter() {
+        items.into_iter().for_each(&mut sink);
+    }
+
+    let stats = shared.0.lock().unwrap().stats;
+    Ok(stats)
+}
+
+/// talks to a single worker, handing its chunk back if it goes away
+fn coordinate(conn: Box<dyn Conn>, job: &Job, options: ServeOptions, shared: &Shared, tx: &mpsc::Sender<Vec<Cell>>) -> io::Result<()> {
+    let (lock, wake) = &**shared;
+    conn.set_timeout(options.timeout)?;
+    let mut conn = Frames::new(conn);
+
+    if conn.read()?.0 != HELLO {
+        return Err(invalid("expected a worker"))
+    }
+    conn.write(JOB, &encode_job(job)?)?;
+
+    loop {
+        let chunk = {
+            let mut queue = lock.lock().unwrap();
+            loop {
+                if let Some(chunk) = queue.waiting.pop_front() {
+                    queue.outstanding.insert(chunk.id, chunk);
+                    break Some(chunk)
+                }
+                if queue.is_done() {
+                    break None
+                }
+                // another worker might still give its chunk back
+                queue = wake.wait_timeout(queue, Duration::from_millis(100)).unwrap().0;
+            }
+        };
+
+        let chunk = match chunk {
+            Some(chunk) => chunk,
+            None => return conn.write(DONE, &[])
+        };
+
+        match work_on(&mut conn, chunk, tx) {
+            Ok(()) => {
+                lock.lock().unwrap().outstanding.remove(&chunk.id);
+            }
+            Err(e) => {
+                let mut queue = lock.lock().unwrap();
+                queue.outstanding.remove(&chunk.id);
+                queue.waiting.push_front(chunk);
+                queue.stats.reassigned += 1;
+                wake.notify_all();
+                return Err(e)
+            }
+        }
+        wake.notify_all();
+    }
+}
+
+fn work_on(conn: &mut Frames, chunk: Chunk, tx: &mpsc::Sender<Vec<Cell>>) -> io::Result<()> {
+    let mut msg = Vec::with_capacity(40);
+    msg.extend_from_slice(&chunk.id.to_le_bytes());
+    msg.extend_from_slice(&(chunk.seed as u64).to_le_bytes());
+    msg.extend_from_slice(&chunk.start.to_le_bytes());
+    msg.extend_from_slice(&chunk.end.to_le_bytes());
+    conn.write(CHUNK, &msg)?;
+
+    let (tag, body) = conn.read()?;
+    let mut body = Decoder(&body);
+    if body.u64()? != chunk.id {
+        return Err(invalid("worker answered for another chunk"))
+    }
+
+    match tag {
+        RESULTS => {
+            let items = (0..body.u32()?).map(|_| body.bytes().map(Cell::from)).collect::<io::Result<_>>()?;
+            tx.send(items).map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "coordinator has stopped"))
+        }
+        FINISHED => Ok(()),
+        _ => Err(invalid("unexpected message from worker"))
+    }
+}
+
+/// Works through whichever chunks the coordinator on the other end of
+/// `conn` hands out, until it's done. Candidates are sent back, or when
+/// `shards` is given, written there as `chunk-ID.txt`. Returns how
+/// many chunks this worker finished
+pub fn work(conn: Box<dyn Conn>, shards: Option<&Path>) -> io::Result<usize> {
+    let mut conn = Frames::new(conn);
+    conn.write(HELLO, &[])?;
+
+    let (tag, body) = conn.read()?;
+    if tag != JOB {
+        return Err(invalid("expected a job"))
+    }
+    let job = decode_job(&body)?;
+    let mut keyspaces = HashMap::new();
+    let mut finished = 0;
+
+    loop {
+        let (tag, body) = conn.read()?;
+        match tag {
+            DONE => return Ok(finished),
+            CHUNK => {},
+            _ => return Err(invalid("unexpected message from coordinator"))
+        }
+
+        let mut body = Decoder(&body);
+        let (id, seed, start, end) = (body.u64()?, body.u64()? as usize, body.u128()?, body.u128()?);
+        let keyspace = match keyspaces.entry(seed) {
+            Entry::Occupied(x) => x.into_mut(),
+            Entry::Vacant(x) => x.insert(job.keyspace(seed)?)
+        };
+        let items = (start..end).filter_map(|idx| keyspace.get(idx));
+
+        match shards {
+            Some(dir) => {
+                write_shard(&dir.join(format!("chunk-{}.txt", id)), items)?;
+                conn.write(FINISHED, &id.to_le_bytes())?;
+            }
+            None => {
+                let count = u32::try_from(end - start).map_err(|_| invalid("chunk is too large to send back"))?;
+                let mut msg = id.to_le_bytes().to_vec();
+                msg.extend_from_slice(&count.to_le_bytes());
+                for item in items {
+                    put_bytes(&mut msg, &item)?;
+                }
+                conn.write(RESULTS, &msg)?;
+            }
+        }
+        finished += 1;
+    }
+}
+
+/// written aside and moved in place, so a shard is either whole or missing
+fn write_shard<I>(path: &Path, items: I) -> io::Result<()>
+where I: Iterator<Item=Cell>
+{
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+
+    let mut out = BufWriter::new(fs::File::create(&partial)?);
+    for item in items {
+        out.write_all(&item)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    drop(out);
+    fs::rename(&partial, path)
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn put_len(buf: &mut Vec<u8>, len: usize) -> io::Result<()> {
+    let len = u32::try_from(len).map_err(|_| invalid("too long to send"))?;
+    buf.extend_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) -> io::Result<()> {
+    put_len(buf, bytes.len())?;
+    buf.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn encode_job(job: &Job) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    put_bytes(&mut buf, job.pattern.as_bytes())?;
+    put_bytes(&mut buf, job.closure.name().as_bytes())?;
+
+    put_len(&mut buf, job.rules.len())?;
+    for (key, rule) in &job.rules {
+        buf.push(*key);
+        put_bytes(&mut buf, rule)?;
+    }
+
+    put_len(&mut buf, job.seeds.len())?;
+    for seed in &job.seeds {
+        put_bytes(&mut buf, seed)?;
+    }
+    Ok(buf)
+}
+
+fn decode_job(buf: &[u8]) -> io::Result<Job> {
+    let mut buf = Decoder(buf);
+    let pattern = String::from_utf8(buf.bytes()?.to_vec()).map_err(|_| invalid("pattern isn't utf-8"))?;
+    let closure = std::str::from_utf8(buf.bytes()?).ok()
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(|| invalid("unknown closure"))?;
+
+    let rules = (0..buf.u32()?)
+        .map(|_| Ok((buf.u8()?, RuleCell::from(buf.bytes()?))))
+        .collect::<io::Result<_>>()?;
+    let seeds = (0..buf.u32()?)
+        .map(|_| buf.bytes().map(Cell::from))
+        .collect::<io::Result<_>>()?;
+
+    Ok(Job { pattern, closure, rules, seeds })
+}
+
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(invalid("message is cut short"))
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn u128(&mut self) -> io::Result<u128> {
+        Ok(u128::from_le_bytes(self.take(16)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+/// messages as a length, a tag and a body
+struct Frames {
+    input: BufReader<Box<dyn Conn>>,
+}
+
+impl Frames {
+    fn new(conn: Box<dyn Conn>) -> Self {
+        Self { input: BufReader::new(conn) }
+    }
+
+    fn read(&mut self) -> io::Result<(u8, Vec<u8>)> {
+        let mut head = [0; 5];
+        self.input.read_exact(&mut head)?;
+
+        let len = u32::from_le_bytes(head[..4].try_into().unwrap()) as usize;
+        if len > MAX_FRAME {
+            return Err(invalid("frame is longer than allowed"))
+        }
+        let mut body = vec![0; len];
+        self.input.read_exact(&mut body)?;
+        Ok((head[4], body))
+    }
+
+    fn write(&mut self, tag: u8, body: &[u8]) -> io::Result<()> {
+        if body.len() > MAX_FRAME {
+            return Err(invalid("message is longer than a frame allows"))
+        }
+        let mut frame = Vec::with_capacity(body.len() + 5);
+        put_len(&mut frame, body.len())?;
+        frame.push(tag);
+        frame.extend_from_slice(body);
+
+        let conn = self.input.get_mut();
+        conn.write_all(&frame)?;
+        conn.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hashbrown::HashSet;
+
+    fn job() -> Job {
+        let rules: Rulebook = vec![
+            (b'a', RuleCell::from(&b"4@"[..])),
+            (b'e', RuleCell::from(&b"3"[..])),
+            (b's', RuleCell::from(&b"5$"[..])),
+        ].into();
+        Job::new("const", &rules, vec![Cell::from(&b"seesaw"[..]), Cell::from(&b"ease"[..])])
+    }
+
+    fn expected(job: &Job) -> HashSet<Cell> {
+        (0..job.seeds.len())
+            .flat_map(|seed| {
+                let keyspace = job.keyspace(seed).unwrap();
+                (0..keyspace.size()).filter_map(move |idx| keyspace.get(idx))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn behavior_remote() {
+        let job = job();
+        let listener = Listener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.address().unwrap();
+        let options = ServeOptions { chunk: 7, timeout: Some(Duration::from_secs(5)) };
+
+        let server = {
+            let job = job.clone();
+            thread::spawn(move || {
+                let mut found = Vec::new();
+                let stats = serve(&listener, &job, options, |x| found.push(x)).unwrap();
+                (found, stats)
+            })
+        };
+
+        // takes a chunk and disappears without answering
+        let mut quitter = Frames::new(connect(&addr).unwrap());
+        quitter.write(HELLO, &[]).unwrap();
+        assert_eq!(quitter.read().unwrap().0, JOB);
+        assert_eq!(quitter.read().unwrap().0, CHUNK);
+        drop(quitter);
+
+        let workers: Vec<_> = (0..3).map(|_| {
+            let addr = addr.clone();
+            thread::spawn(move || work(connect(&addr).unwrap(), None).unwrap())
+        }).collect();
+
+        let finished: usize = workers.into_iter().map(|x| x.join().unwrap()).sum();
+        let (found, stats) = server.join().unwrap();
+
+        assert_eq!(stats.reassigned, 1);
+        assert_eq!(finished, stats.chunks);
+        assert_eq!(found.len(), expected(&job).len());
+        assert_eq!(found.into_iter().collect::<HashSet<_>>(), expected(&job));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn behavior_remote_shards() {
+        let job = job();
+        let dir = tempfile::tempdir().unwrap();
+        let listener = Listener::bind(&Address::Unix(dir.path().join("serve.sock"))).unwrap();
+        let addr = listener.address().unwrap();
+
+        let shards = dir.path().to_path_buf();
+        let worker = thread::spawn(move || work(connect(&addr).unwrap(), Some(&shards)).unwrap());
+
+        let mut sent = 0;
+        let stats = serve(&listener, &job, ServeOptions::default(), |_| sent += 1).unwrap();
+        assert_eq!(worker.join().unwrap(), stats.chunks);
+        assert_eq!(sent, 0);
+
+        let mut found = HashSet::new();
+        for id in 0..stats.chunks {
+            let shard = fs::read(dir.path().join(format!("chunk-{}.txt", id))).unwrap();
+            found.extend(shard.split(|x| *x == b'\n').filter(|x| !x.is_empty()).map(Cell::from));
+        }
+        assert_eq!(found, expected(&job));
+    }
+
+    #[test]
+    fn job_closure() {
+        let mut job = Job::new("const", &crate::testutil::chains(), vec![Cell::from(&b"as"[..])]);
+        assert_eq!(job.keyspace(0).unwrap().size(), 4 * 2);
+
+        job.closure = Closure::SingleHop;
+        assert_eq!(decode_job(&encode_job(&job).unwrap()).unwrap(), job);
+        assert_eq!(job.keyspace(0).unwrap().size(), 2 * 2);
+
+        job.closure = Closure::Transitive;
+        assert_eq!(decode_job(&encode_job(&job).unwrap()).unwrap(), job);
+        assert_eq!(job.keyspace(0).unwrap().size(), 4 * 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_long_frames() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        sender.write_all(&(MAX_FRAME as u32 + 1).to_le_bytes()).unwrap();
+        sender.write_all(&[JOB]).unwrap();
+
+        let e = Frames::new(Box::new(receiver)).read().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        let listener = Listener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let options = ServeOptions { chunk: MAX_CHUNK + 1, ..Default::default() };
+        assert!(serve(&listener, &job(), options, |_| {}).is_err());
+    }
+}
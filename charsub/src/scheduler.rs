@@ -1,75 +1,169 @@
 use crate::{
     unit::Permutation,
     Cell as CharBuf,
-    RuleCell,
-    patterns::Handler,
+    patterns::{Handler, Prune},
     cursor::{Cursor, Output},
     Rulebook,
-    Length
+    Error,
+    Length,
+    Drain,
+    Snapshot,
+    shard::ShardedSet,
+    storage::{Storage, Memory, Cells},
+    cancel::CancelToken
 };
 
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Condvar};
 use hashbrown::HashSet;
+use rayon::prelude::*;
 
 pub trait Scheduler {
 
     fn schedule(&mut self, permute: Permutation);
     fn clean_state(&self) -> bool;
 
-    fn new_generation<H>(&mut self, rules: &Rulebook) where H: Handler;
+    /// permutates the candidates new to the last generation, returns
+    /// how many candidates that came up with, duplicates included
+    fn new_generation<H>(&mut self, rules: &Rulebook) -> usize where H: Handler;
 
     fn push(&mut self, item: CharBuf);
 
-    fn permutate_cell<H>(&mut self, cursor: &mut Cursor)
+    /// settles the candidates pushed since the last generation,
+    /// so `Length::length` counts them
+    fn settle(&mut self) {}
+
+    /// stops permutating once `token` is cancelled, a cancelled
+    /// generation returns early with whatever it had found.
+    /// candidates it didn't get to are left unpermutated
+    fn set_cancel(&mut self, token: CancelToken);
+
+    /// leaves candidates `pruner` gives up on unpermutated,
+    /// nothing they'd turn into would be worth emitting
+    fn set_pruner(&mut self, pruner: Arc<dyn Prune>);
+
+    /// the first error met since this was last asked, see `Storage::take_error`
+    fn take_error(&mut self) -> Option<Error> {
+        None
+    }
+
+    /// blocks until every job handed out by `schedule` has finished
+    fn wait_idle(&self) {
+        while !self.clean_state() {
+            std::thread::yield_now();
+        }
+    }
+
+    /// returns how many candidates the scheduled permutations come up with
+    fn permutate_cell<H>(&mut self, cursor: &mut Cursor) -> usize
     where
         H: Handler + std::fmt::Debug
     {
-        loop {
-            match cursor.step() {
-                Output::Permute(permute) => {
-                    if H::handle(&permute) {
-                        continue
-                    }
-                    self.schedule(permute);
-                },
+        permutations::<H, _>(cursor, |permute| self.schedule(permute))
+    }
+}
 
-                Output::NoPermute(_idx) => {
+/// walks the cursor to the end of its line,
+/// handing every permutation the handler lets through to `each`.
+/// returns how many candidates those come up with
+pub(crate) fn permutations<H, F>(cursor: &mut Cursor, mut each: F) -> usize
+where
+    H: Handler,
+    F: FnMut(Permutation)
+{
+    let mut produced = 0;
+    loop {
+        match cursor.step() {
+            Output::Permute(permute) => {
+                if H::handle(&permute) {
                     continue
-                },
-                Output::EndOfLine => break
-            }
+                }
+                produced += permute.changes();
+                each(permute);
+            },
+
+            Output::NoPermute(_idx) => {
+                continue
+            },
+            Output::EndOfLine => break
         }
-
     }
+    produced
 }
 
+/// whether `pruner` gives up on `item`
+fn pruned<H>(pruner: &Option<Arc<dyn Prune>>, item: &CharBuf, rules: &Rulebook) -> bool
+where H: Handler
+{
+    pruner.as_ref().is_some_and(|x| !x.viable(item, rules, H::handle))
+}
 
+/// Runs every generation on the calling thread,
+/// keeping candidates in whichever `Storage` it was given
 #[derive(Debug, Default)]
-pub struct SingleThread {
-    buf: HashSet<CharBuf>   
+pub struct SingleThread<S = Memory> {
+    buf: S,
+    cancel: CancelToken,
+    pruner: Option<Arc<dyn Prune>>
 }
 
 impl SingleThread {
     pub fn new() -> Self {
-        Self {
-            buf: HashSet::new()
-        }
+        Self::with_storage(Memory::new())
+    }
+}
+
+impl<S> SingleThread<S>
+where S: Storage
+{
+    pub fn with_storage(buf: S) -> Self {
+        Self { buf, cancel: CancelToken::new(), pruner: None }
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.buf
     }
 }
 
-impl Into<HashSet<CharBuf>> for SingleThread {
-    fn into(self) -> HashSet<CharBuf> {
-        self.buf
+impl<S> From<SingleThread<S>> for HashSet<CharBuf>
+where S: Storage
+{
+    fn from(mut x: SingleThread<S>) -> HashSet<CharBuf> {
+        x.buf.drain().collect()
     }
 }
 
-impl Length for SingleThread {
+impl<S> Length for SingleThread<S>
+where S: Storage
+{
     fn length(&self) -> usize {
         self.buf.len()
     }
 }
 
-impl Scheduler for SingleThread {
+impl<S> Drain for SingleThread<S>
+where S: Storage
+{
+    fn drain(&mut self) -> Cells {
+        self.buf.drain()
+    }
+}
+
+impl<S> Snapshot for SingleThread<S>
+where S: Storage
+{
+    fn fresh(&mut self) -> Cells {
+        self.buf.commit();
+        self.buf.fresh()
+    }
+
+    fn snapshot(&mut self) -> Cells {
+        self.buf.snapshot()
+    }
+}
+
+impl<S> Scheduler for SingleThread<S>
+where S: Storage
+{
     fn schedule(&mut self, mut permute: Permutation) {
         while let Some(x) = permute.commit() {
             self.buf.insert(x.clone());
@@ -80,45 +174,130 @@ impl Scheduler for SingleThread {
         self.buf.insert(item);
     }
 
+    fn settle(&mut self) {
+        self.buf.commit();
+    }
+
     fn clean_state(&self) -> bool {
         true
     }
 
-    fn new_generation<H>(&mut self, rules: &Rulebook) where H: Handler {
-        let mut buf = std::mem::replace(&mut self.buf, HashSet::new());
-        //println!("{:?}", buf.clone().iter().map(|x| String::from_utf8_lossy(&x)).collect::<Vec<_>>());
-        for item in &buf {
+    /// only the candidates which were new to
+    /// the last generation are permutated
+    fn new_generation<H>(&mut self, rules: &Rulebook) -> usize where H: Handler {
+        let mut produced = 0;
+        self.buf.commit();
+        for item in self.buf.take_fresh() {
+            if self.cancel.is_cancelled() {
+                break
+            }
+            if pruned::<H>(&self.pruner, &item, rules) {
+                continue
+            }
             let mut cursor = Cursor::new(&item, rules);
-            self.permutate_cell::<H>(&mut cursor);
+            produced += self.permutate_cell::<H>(&mut cursor);
         }
+        self.buf.commit();
+        produced
+    }
+
+    fn set_cancel(&mut self, token: CancelToken) {
+        self.cancel = token;
+    }
+
+    fn set_pruner(&mut self, pruner: Arc<dyn Prune>) {
+        self.pruner = Some(pruner);
+    }
 
-        self.buf.extend(buf.drain());
+    fn take_error(&mut self) -> Option<Error> {
+        self.buf.take_error()
     }
 }
 
-fn shared_buf() -> Arc<Mutex<HashSet<CharBuf>>> {
-    Arc::new(Mutex::new(HashSet::new()))
+fn shared_buf() -> Arc<Mutex<Memory>> {
+    Arc::new(Mutex::new(Memory::new()))
+}
+
+/// Counts jobs which have been handed off to
+/// another thread or task, but haven't finished yet
+#[derive(Debug, Default)]
+pub struct Pending {
+    count: Mutex<usize>,
+    idle: Condvar,
+    notify: tokio::sync::Notify
+}
+
+impl Pending {
+    /// registers a job, which is accounted for
+    /// until the returned ticket is dropped
+    pub fn ticket(self: &Arc<Self>) -> Ticket {
+        *self.count.lock().unwrap() += 1;
+        Ticket(self.clone())
+    }
+
+    pub fn is_idle(&self) -> bool {
+        *self.count.lock().unwrap() == 0
+    }
+
+    pub fn wait(&self) {
+        let mut count = self.count.lock().unwrap();
+        while *count > 0 {
+            count = self.idle.wait(count).unwrap();
+        }
+    }
+
+    pub async fn wait_async(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_idle() {
+                return
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Held by a job for as long as it's running,
+/// the job is finished once this is dropped
+#[derive(Debug)]
+pub struct Ticket(Arc<Pending>);
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let mut count = self.0.count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.0.idle.notify_all();
+            self.0.notify.notify_waiters();
+        }
+    }
 }
 
 
 #[derive(Debug, Default)]
 pub struct MultithreadMutex {
     pool: threadpool::ThreadPool,
-    buf: Arc<Mutex<HashSet<CharBuf>>>
+    buf: Arc<Mutex<Memory>>,
+    pending: Arc<Pending>,
+    cancel: CancelToken,
+    pruner: Option<Arc<dyn Prune>>
 }
 
 impl MultithreadMutex {
     pub fn new(workers: usize) -> Self {
         Self {
             pool: threadpool::ThreadPool::new(workers),
-            buf: shared_buf()
+            buf: shared_buf(),
+            pending: Default::default(),
+            cancel: CancelToken::new(),
+            pruner: None
         }
     }
 }
 
-impl Into<HashSet<CharBuf>> for MultithreadMutex {
-    fn into(self) -> HashSet<CharBuf> {
-        self.buf.lock().unwrap().clone()
+impl From<MultithreadMutex> for HashSet<CharBuf> {
+    fn from(x: MultithreadMutex) -> HashSet<CharBuf> {
+        std::mem::take(&mut *x.buf.lock().unwrap()).into()
     }
 }
 
@@ -128,24 +307,64 @@ impl Length for MultithreadMutex {
     }
 }
 
+impl Drain for MultithreadMutex {
+    fn drain(&mut self) -> Cells {
+        self.buf.lock().unwrap().drain()
+    }
+}
+
+impl Snapshot for MultithreadMutex {
+    fn fresh(&mut self) -> Cells {
+        let mut buf = self.buf.lock().unwrap();
+        buf.commit();
+        buf.fresh()
+    }
+
+    fn snapshot(&mut self) -> Cells {
+        self.buf.lock().unwrap().snapshot()
+    }
+}
+
 impl Scheduler for MultithreadMutex {
 
-    fn new_generation<H>(&mut self, rules: &Rulebook) where H: Handler { 
-        let mut lock: Vec<_> = self.buf.lock().unwrap().drain().collect();
-        
-        for item in &lock {
+    fn new_generation<H>(&mut self, rules: &Rulebook) -> usize where H: Handler {
+        let mut produced = 0;
+        let fresh = {
+            let mut buf = self.buf.lock().unwrap();
+            buf.commit();
+            buf.take_fresh()
+        };
+
+        for item in fresh {
+            if self.cancel.is_cancelled() {
+                break
+            }
+            if pruned::<H>(&self.pruner, &item, rules) {
+                continue
+            }
             let mut cursor = Cursor::new(&item, rules);
-            self.permutate_cell::<H>(&mut cursor);
+            produced += self.permutate_cell::<H>(&mut cursor);
         }
 
-        self.buf.lock().unwrap().extend(lock.drain(..));
+        self.wait_idle();
+        self.buf.lock().unwrap().commit();
+        produced
+    }
+
+    fn set_cancel(&mut self, token: CancelToken) {
+        self.cancel = token;
+    }
 
+    fn set_pruner(&mut self, pruner: Arc<dyn Prune>) {
+        self.pruner = Some(pruner);
     }
 
     fn schedule(&mut self, mut permute: Permutation) {
         let buf_ref = self.buf.clone();
+        let ticket = self.pending.ticket();
 
-        self.pool.execute(move || {    
+        self.pool.execute(move || {
+            let _ticket = ticket;
             let mut lock = buf_ref.lock().unwrap();
             while let Some(x) = permute.commit() {
                 lock.insert(x.clone());
@@ -154,12 +373,122 @@ impl Scheduler for MultithreadMutex {
     }
 
     fn clean_state(&self) -> bool {
-        true
+        self.pending.is_idle()
+    }
+
+    fn wait_idle(&self) {
+        self.pending.wait()
     }
 
     fn push(&mut self, item: CharBuf) {
         self.buf.lock().unwrap().insert(item);
     }
+
+    fn settle(&mut self) {
+        self.buf.lock().unwrap().commit();
+    }
+}
+
+/// Data parallel scheduler, each generation only permutates
+/// the cells which were new to the last one, spreading them
+/// across a work-stealing pool. Results are kept in a hash-sharded
+/// set so workers seldom wait on each other's locks.
+#[derive(Debug)]
+pub struct WorkStealing {
+    pool: rayon::ThreadPool,
+    buf: ShardedSet,
+    cancel: CancelToken,
+    pruner: Option<Arc<dyn Prune>>
+}
+
+impl WorkStealing {
+    /// `workers` of 0 uses one worker per core,
+    /// refuses when the workers can't be started
+    pub fn new(workers: usize) -> Result<Self, Error> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(workers)
+            .build()
+            .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+
+        Ok(Self {
+            buf: ShardedSet::new(pool.current_num_threads() * 4),
+            pool,
+            cancel: CancelToken::new(),
+            pruner: None
+        })
+    }
+}
+
+impl From<WorkStealing> for HashSet<CharBuf> {
+    fn from(x: WorkStealing) -> HashSet<CharBuf> {
+        x.buf.into()
+    }
+}
+
+impl Length for WorkStealing {
+    fn length(&self) -> usize {
+        self.buf.len()
+    }
+}
+
+impl Drain for WorkStealing {
+    fn drain(&mut self) -> Cells {
+        let buf: HashSet<CharBuf> = std::mem::take(&mut self.buf).into();
+        Box::new(buf.into_iter())
+    }
+}
+
+impl Snapshot for WorkStealing {
+    fn fresh(&mut self) -> Cells {
+        Box::new(self.buf.fresh().into_iter())
+    }
+
+    fn snapshot(&mut self) -> Cells {
+        Box::new(self.buf.snapshot().into_iter())
+    }
+}
+
+impl Scheduler for WorkStealing {
+    fn new_generation<H>(&mut self, rules: &Rulebook) -> usize where H: Handler {
+        let frontier = self.buf.take_fresh();
+        let (buf, cancel, pruner) = (&self.buf, &self.cancel, &self.pruner);
+
+        self.pool.install(|| {
+            frontier.par_iter().map(|item| {
+                if cancel.is_cancelled() || pruned::<H>(pruner, item, rules) {
+                    return 0
+                }
+                let mut cursor = Cursor::new(item, rules);
+                permutations::<H, _>(&mut cursor, |mut permute| {
+                    while let Some(x) = permute.commit() {
+                        buf.insert(x.clone());
+                    }
+                })
+            }).sum()
+        })
+    }
+
+    fn schedule(&mut self, mut permute: Permutation) {
+        while let Some(x) = permute.commit() {
+            self.buf.insert(x.clone());
+        }
+    }
+
+    fn clean_state(&self) -> bool {
+        true
+    }
+
+    fn push(&mut self, item: CharBuf) {
+        self.buf.insert(item);
+    }
+
+    fn set_cancel(&mut self, token: CancelToken) {
+        self.cancel = token;
+    }
+
+    fn set_pruner(&mut self, pruner: Arc<dyn Prune>) {
+        self.pruner = Some(pruner);
+    }
 }
 
 lazy_static! {
@@ -169,7 +498,10 @@ lazy_static! {
 #[derive(Debug)]
 pub struct TokioMutex {
     runtime: Arc<tokio::runtime::Runtime>,
-    buf: Arc<Mutex<HashSet<CharBuf>>>
+    buf: Arc<Mutex<Memory>>,
+    pending: Arc<Pending>,
+    cancel: CancelToken,
+    pruner: Option<Arc<dyn Prune>>
 }
 
 impl Default for TokioMutex {
@@ -182,13 +514,46 @@ impl TokioMutex {
 
     #[inline]
     pub fn new() -> Self {
-        Self { runtime: RT.clone(), buf: Arc::new(Mutex::new(Default::default())) }
+        Self::with_runtime(RT.clone())
+    }
+
+    /// spawns jobs onto a runtime of its own with `workers` threads
+    /// instead of the one shared by every `TokioMutex`, `workers` of 0
+    /// uses one per core. it's always a multi-threaded runtime, as
+    /// `wait_idle` blocks the caller until the jobs are done
+    pub fn with_workers(workers: usize) -> Result<Self, Error> {
+        let mut runtime = tokio::runtime::Builder::new_multi_thread();
+        if workers > 0 {
+            runtime.worker_threads(workers);
+        }
+        Ok(Self::with_runtime(Arc::new(runtime.enable_all().build()?)))
+    }
+
+    fn with_runtime(runtime: Arc<tokio::runtime::Runtime>) -> Self {
+        Self { runtime, buf: shared_buf(), pending: Default::default(), cancel: CancelToken::new(), pruner: None }
+    }
+
+    /// resolves once every spawned job has finished,
+    /// for callers who can't block their own runtime with `wait_idle`
+    pub async fn idle(&self) {
+        self.pending.wait_async().await
+    }
+}
+
+/// a runtime of its own is shut down without waiting,
+/// as dropping it from an async context would panic
+impl Drop for TokioMutex {
+    fn drop(&mut self) {
+        let runtime = std::mem::replace(&mut self.runtime, RT.clone());
+        if let Ok(runtime) = Arc::try_unwrap(runtime) {
+            runtime.shutdown_background();
+        }
     }
 }
 
-impl Into<HashSet<CharBuf>> for TokioMutex {
-    fn into(self) -> HashSet<CharBuf> {
-        self.buf.lock().unwrap().clone()
+impl From<TokioMutex> for HashSet<CharBuf> {
+    fn from(x: TokioMutex) -> HashSet<CharBuf> {
+        std::mem::take(&mut *x.buf.lock().unwrap()).into()
     }
 }
 
@@ -198,20 +563,63 @@ impl Length for TokioMutex {
     }
 }
 
+impl Drain for TokioMutex {
+    fn drain(&mut self) -> Cells {
+        self.buf.lock().unwrap().drain()
+    }
+}
+
+impl Snapshot for TokioMutex {
+    fn fresh(&mut self) -> Cells {
+        let mut buf = self.buf.lock().unwrap();
+        buf.commit();
+        buf.fresh()
+    }
+
+    fn snapshot(&mut self) -> Cells {
+        self.buf.lock().unwrap().snapshot()
+    }
+}
+
 impl Scheduler for TokioMutex {
-    fn new_generation<H>(&mut self, rules: &Rulebook) where H: Handler { 
-        let lock: Vec<_> = self.buf.lock().unwrap().drain().collect();
-        
-        for item in lock {
+    fn new_generation<H>(&mut self, rules: &Rulebook) -> usize where H: Handler {
+        let mut produced = 0;
+        let fresh = {
+            let mut buf = self.buf.lock().unwrap();
+            buf.commit();
+            buf.take_fresh()
+        };
+
+        for item in fresh {
+            if self.cancel.is_cancelled() {
+                break
+            }
+            if pruned::<H>(&self.pruner, &item, rules) {
+                continue
+            }
             let mut cursor = Cursor::new(&item, rules);
-            self.permutate_cell::<H>(&mut cursor);
+            produced += self.permutate_cell::<H>(&mut cursor);
         }
+
+        self.wait_idle();
+        self.buf.lock().unwrap().commit();
+        produced
+    }
+
+    fn set_cancel(&mut self, token: CancelToken) {
+        self.cancel = token;
+    }
+
+    fn set_pruner(&mut self, pruner: Arc<dyn Prune>) {
+        self.pruner = Some(pruner);
     }
 
     fn schedule(&mut self, mut permute: Permutation) {
         let buf_ref = self.buf.clone();
+        let ticket = self.pending.ticket();
 
         self.runtime.spawn(async move {
+            let _ticket = ticket;
             let mut lock = buf_ref.lock().unwrap();
             while let Some(x) = permute.commit() {
                 lock.insert(x.clone());
@@ -220,117 +628,117 @@ impl Scheduler for TokioMutex {
     }
 
     fn clean_state(&self) -> bool {
-        true
+        self.pending.is_idle()
+    }
+
+    fn wait_idle(&self) {
+        self.pending.wait()
     }
 
     fn push(&mut self, item: CharBuf) {
         self.buf.lock().unwrap().insert(item);
     }
+
+    fn settle(&mut self) {
+        self.buf.lock().unwrap().commit();
+    }
 }
 
+#[test]
+fn work_stealing_behave() {
+    use crate::{RuleCell, patterns::ConstPattern};
 
+    let rules: Rulebook = vec![
+        (b'a', RuleCell::from(&b"4@"[..])),
+        (b's', RuleCell::from(&b"5$"[..])),
+        (b'4', RuleCell::from(&b"Aa"[..])),
+    ].into();
 
-#[derive(Debug)]
-pub struct UnsafeBuf {
-    rt: Arc<tokio::runtime::Runtime>,
-    buf: HashSet<CharBuf>,
-    arc: Arc<usize>
-}
+    let mut single = SingleThread::new();
+    let mut stealing = WorkStealing::new(4).unwrap();
+    single.push(CharBuf::from(&b"sassafras"[..]));
+    stealing.push(CharBuf::from(&b"sassafras"[..]));
 
-impl Default for UnsafeBuf {
-    fn default() -> Self {
-        Self::new()
+    for _ in 0..4 {
+        single.new_generation::<ConstPattern>(&rules);
+        stealing.new_generation::<ConstPattern>(&rules);
+        assert_eq!(single.length(), stealing.length());
     }
-}
 
-impl UnsafeBuf {
-    #[inline]
-    pub fn new() -> Self {
-        Self { ..Default::default() }
-    }
+    let single: HashSet<CharBuf> = single.into();
+    let stealing: HashSet<CharBuf> = stealing.into();
+    assert_eq!(single, stealing);
 }
 
-impl Into<HashSet<CharBuf>> for UnsafeBuf {
-    fn into(self) -> HashSet<CharBuf> {
-        self.buf
-    }
-}
+#[test]
+fn pooled_generations_complete() {
+    use crate::{RuleCell, patterns::ConstPattern};
 
-impl Length for UnsafeBuf {
-    fn length(&self) -> usize {
-        self.buf.len()
-    }
-}
+    let rules: Rulebook = vec![
+        (b'a', RuleCell::from(&b"4@"[..])),
+        (b'o', RuleCell::from(&b"0()"[..])),
+    ].into();
 
+    let mut single = SingleThread::new();
+    let mut pool = MultithreadMutex::new(4);
+    let mut rt = TokioMutex::new();
 
-struct UnsafePtr(*mut HashSet<CharBuf>);
-impl Into<*mut HashSet<CharBuf>> for UnsafePtr {
-    fn into(self) -> *mut HashSet<CharBuf> {
-        self.0
-    }
-}
+    single.push(CharBuf::from(&b"avocado"[..]));
+    pool.push(CharBuf::from(&b"avocado"[..]));
+    rt.push(CharBuf::from(&b"avocado"[..]));
 
-impl From<*mut HashSet<CharBuf>> for UnsafePtr {
-    fn from(x: *mut HashSet<CharBuf>) -> Self {
-        UnsafePtr(x)
-    }
-} 
+    for _ in 0..4 {
+        single.new_generation::<ConstPattern>(&rules);
+        pool.new_generation::<ConstPattern>(&rules);
+        rt.new_generation::<ConstPattern>(&rules);
 
-impl From<&mut HashSet<CharBuf>> for UnsafePtr {
-    fn from(x: &mut HashSet<CharBuf>) -> Self {
-        let temp: *mut HashSet<CharBuf> = &mut *x;
-        UnsafePtr(temp)
+        assert!(pool.clean_state() && rt.clean_state());
+        assert_eq!(single.length(), pool.length());
+        assert_eq!(single.length(), rt.length());
     }
-} 
-
-unsafe impl Send for UnsafePtr {}
-unsafe impl Sync for UnsafePtr {}
+}
 
-impl Scheduler for UnsafeBuf
-where Self: 'static {
-    fn new_generation<H>(&mut self, rules: &Rulebook) where H: Handler { 
-        let lock: Vec<_> = self.buf.drain().collect();
-        
-        for item in lock {
-            let mut cursor = Cursor::new(&item, rules);
-            self.permutate_cell::<H>(&mut cursor);
-        }
-    }
+#[test]
+fn cancelled_generations_stop() {
+    use crate::{RuleCell, patterns::ConstPattern};
+
+    let rules: Rulebook = vec![
+        (b'e', RuleCell::from(&b"3"[..])),
+    ].into();
+
+    let cancel = CancelToken::new();
+    let mut single = SingleThread::new();
+    let mut pool = MultithreadMutex::new(2);
+    let mut stealing = WorkStealing::new(2).unwrap();
+    single.set_cancel(cancel.clone());
+    pool.set_cancel(cancel.clone());
+    stealing.set_cancel(cancel.clone());
+
+    single.push(CharBuf::from(&b"eel"[..]));
+    pool.push(CharBuf::from(&b"eel"[..]));
+    stealing.push(CharBuf::from(&b"eel"[..]));
+    cancel.cancel();
+
+    single.new_generation::<ConstPattern>(&rules);
+    pool.new_generation::<ConstPattern>(&rules);
+    stealing.new_generation::<ConstPattern>(&rules);
+    assert_eq!((single.length(), pool.length(), stealing.length()), (1, 1, 1));
+}
 
-    fn schedule(&mut self, mut permute: Permutation) {
-            // let ptr: *mut HashSet<CharBuf> = &mut self.buf;
-            // let ptr2: *mut HashSet<CharBuf> = &mut self.buf;
-            
-            // unsafe {
-            //     let buf: &mut HashSet<CharBuf> = &mut *ptr;
-            //     let buf2: &mut HashSet<CharBuf> = &mut *ptr2;
-            // }
-            let arc = self.arc.clone();
-            let ptr: *mut HashSet<CharBuf> = &mut self.buf;
-            let ptr = UnsafePtr(ptr);
-
-            self.rt.spawn(async move {
-
-                while let Some(x) = permute.commit() {
-                    unsafe {
-                        let buf: &mut HashSet<CharBuf> = &mut *ptr.0; 
-                        buf.insert(x.clone());
-                    }
-                }
-                let _ = arc.overflowing_add(1); // so the arc isn't optimized out
-            });
-        }
+#[tokio::test]
+async fn generations_inside_a_runtime() {
+    use crate::{RuleCell, patterns::ConstPattern};
 
-    fn clean_state(&self) -> bool {
-        Arc::strong_count(&self.arc) == 1
-    }
+    let rules: Rulebook = vec![(b'a', RuleCell::from(&b"4@"[..]))].into();
+    let mut single = SingleThread::new();
+    let mut rt = TokioMutex::with_workers(1).unwrap();
+    single.push(CharBuf::from(&b"banana"[..]));
+    rt.push(CharBuf::from(&b"banana"[..]));
 
-    fn push(&mut self, item: CharBuf) {
-        self.buf.insert(item);
+    // the caller's runtime only has the one thread this runs on
+    for _ in 0..4 {
+        single.new_generation::<ConstPattern>(&rules);
+        rt.new_generation::<ConstPattern>(&rules);
+        assert_eq!(single.length(), rt.length());
     }
 }
-
-#[test]
-fn unsafe_buf_behave() {
-    
-}
\ No newline at end of file
@@ -0,0 +1,22 @@
+/// Serializes a type as the string `$show` writes, and
+/// deserializes it from one `$parse` reads
+macro_rules! serde_string {
+    ($kind:ty, $show:expr, $parse:expr) => {
+        impl serde::Serialize for $kind {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let show: fn(&$kind) -> String = $show;
+                serializer.serialize_str(&show(self))
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $kind {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let parse: fn(&str) -> Result<$kind, String> = $parse;
+                let text = <String as serde::Deserialize>::deserialize(deserializer)?;
+                parse(&text).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+pub(crate) use serde_string;
@@ -0,0 +1,100 @@
+use crate::Cell;
+
+use std::{
+    hash::BuildHasher,
+    sync::Mutex
+};
+
+use hashbrown::{HashSet, hash_map::DefaultHashBuilder};
+
+#[derive(Debug, Default)]
+struct Shard {
+    set: HashSet<Cell>,
+    fresh: Vec<Cell>
+}
+
+/// A set split into independently locked shards,
+/// where a cell always lands in the shard its hash points to.
+/// Cells which were new to the set are remembered
+/// until they're taken with `take_fresh`
+#[derive(Debug)]
+pub struct ShardedSet {
+    hasher: DefaultHashBuilder,
+    shards: Vec<Mutex<Shard>>
+}
+
+impl ShardedSet {
+    pub fn new(shards: usize) -> Self {
+        Self {
+            hasher: DefaultHashBuilder::default(),
+            shards: (0..shards.max(1)).map(|_| Mutex::new(Shard::default())).collect()
+        }
+    }
+
+    fn shard_of(&self, item: &Cell) -> &Mutex<Shard> {
+        &self.shards[self.hasher.hash_one(item) as usize % self.shards.len()]
+    }
+
+    /// returns `true` if `item` wasn't in the set yet
+    pub fn insert(&self, item: Cell) -> bool {
+        let mut shard = self.shard_of(&item).lock().unwrap();
+        if shard.set.contains(&item) {
+            return false
+        }
+        shard.fresh.push(item.clone());
+        shard.set.insert(item)
+    }
+
+    /// takes every cell inserted since the last call
+    pub fn take_fresh(&self) -> Vec<Cell> {
+        let mut fresh = Vec::new();
+        for shard in &self.shards {
+            fresh.append(&mut shard.lock().unwrap().fresh);
+        }
+        fresh
+    }
+
+    /// copies every cell inserted since `take_fresh` was last called
+    pub fn fresh(&self) -> Vec<Cell> {
+        let mut fresh = Vec::new();
+        for shard in &self.shards {
+            fresh.extend(shard.lock().unwrap().fresh.iter().cloned());
+        }
+        fresh
+    }
+
+    /// copies every cell in the set
+    pub fn snapshot(&self) -> Vec<Cell> {
+        let mut all = Vec::new();
+        for shard in &self.shards {
+            all.extend(shard.lock().unwrap().set.iter().cloned());
+        }
+        all
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter()
+            .map(|shard| shard.lock().unwrap().set.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for ShardedSet {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl From<ShardedSet> for HashSet<Cell> {
+    fn from(x: ShardedSet) -> HashSet<Cell> {
+        let mut set = HashSet::new();
+        for shard in x.shards {
+            set.extend(shard.into_inner().unwrap().set);
+        }
+        set
+    }
+}
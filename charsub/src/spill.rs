@@ -0,0 +1,439 @@
+use crate::{
+    Cell,
+    Error,
+    storage::{Storage, Cells}
+};
+
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex}
+};
+
+use hashbrown::HashSet;
+
+/// bookkeeping cost of a candidate held in memory, on top of its bytes
+const OVERHEAD: usize = std::mem::size_of::<Cell>() + 16;
+/// most runs merged at once, past this they're merged down into one
+const FAN_IN: usize = 64;
+
+/// Storage holding at most `budget` bytes of candidates in memory,
+/// beyond that candidates are spilled as sorted runs into a temporary
+/// directory. Committing merges the runs against everything settled
+/// so far (an external sort-unique), so duplicates never reach disk twice.
+#[derive(Debug)]
+pub struct Spill {
+    budget: usize,
+    dir: tempfile::TempDir,
+    run_ctr: usize,
+
+    mem: HashSet<Cell>,
+    mem_bytes: usize,
+    runs: Vec<Run>,
+
+    all: Option<Run>,
+    fresh: Vec<Run>,
+    failed: Failed,
+}
+
+/// The first I/O error met, shared with the readers handed out
+/// so a failed read can be reported once they're done with
+#[derive(Debug, Clone, Default)]
+struct Failed(Arc<Mutex<Option<io::Error>>>);
+
+impl Failed {
+    fn record(&self, e: io::Error) {
+        self.0.lock().unwrap().get_or_insert(e);
+    }
+
+    fn take(&self) -> Option<io::Error> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+impl Spill {
+    /// spills into the system's temporary directory
+    pub fn new(budget: usize) -> io::Result<Self> {
+        Self::new_in(budget, std::env::temp_dir())
+    }
+
+    /// spills into a fresh directory under `dir`,
+    /// which is removed along with the storage
+    pub fn new_in<P: AsRef<Path>>(budget: usize, dir: P) -> io::Result<Self> {
+        Ok(Self {
+            budget,
+            dir: tempfile::Builder::new().prefix("charsub").tempdir_in(dir)?,
+            run_ctr: 0,
+            mem: HashSet::new(),
+            mem_bytes: 0,
+            runs: Vec::new(),
+            all: None,
+            fresh: Vec::new(),
+            failed: Failed::default(),
+        })
+    }
+
+    /// amount of sorted runs currently on disk
+    pub fn runs(&self) -> usize {
+        self.runs.len() + self.fresh.len() + self.all.is_some() as usize
+    }
+
+    fn writer(&mut self) -> io::Result<RunWriter> {
+        self.run_ctr += 1;
+        RunWriter::create(self.dir.path().join(format!("{}.run", self.run_ctr)))
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        let mut sorted: Vec<Cell> = self.mem.drain().collect();
+        sorted.sort_unstable();
+        self.mem_bytes = 0;
+
+        let mut out = self.writer()?;
+        for item in &sorted {
+            out.write(item)?;
+        }
+        self.runs.push(out.finish()?);
+
+        if self.runs.len() >= FAN_IN {
+            let failed = &self.failed;
+            let mut merge = Merge::new(self.runs.drain(..).map(|run| RunReader::open(run, failed)).collect::<io::Result<_>>()?);
+            let mut out = self.writer()?;
+            while let Some(item) = merge.next_item()? {
+                out.write(&item)?;
+            }
+            self.runs.push(out.finish()?);
+        }
+        Ok(())
+    }
+
+    fn try_commit(&mut self) -> io::Result<usize> {
+        if !self.mem.is_empty() {
+            self.spill()?;
+        }
+        if self.runs.is_empty() {
+            return Ok(0)
+        }
+
+        let failed = &self.failed;
+        let mut pending = Merge::new(self.runs.drain(..).map(|run| RunReader::open(run, failed)).collect::<io::Result<_>>()?);
+        let mut all = match self.all.take() {
+            Some(run) => Some(RunReader::open(run, &self.failed)?),
+            None => None
+        };
+
+        let mut next_all = self.writer()?;
+        let mut fresh = self.writer()?;
+        let mut settled = all.as_mut().map(|x| x.next_item()).transpose()?.flatten();
+
+        while let Some(item) = pending.next_item()? {
+            while let Some(old) = settled.take() {
+                match old.cmp(&item) {
+                    Ordering::Less => {
+                        next_all.write(&old)?;
+                        settled = all.as_mut().unwrap().next_item()?;
+                    }
+                    _ => {
+                        settled = Some(old);
+                        break
+                    }
+                }
+            }
+
+            if settled.as_ref() != Some(&item) {
+                next_all.write(&item)?;
+                fresh.write(&item)?;
+            }
+        }
+
+        while let Some(old) = settled {
+            next_all.write(&old)?;
+            settled = all.as_mut().unwrap().next_item()?;
+        }
+
+        let fresh = fresh.finish()?;
+        let new = fresh.len;
+        self.all = Some(next_all.finish()?);
+        self.fresh.push(fresh);
+        Ok(new)
+    }
+
+    /// hands out `readers` once they've all opened,
+    /// otherwise records why and hands out nothing
+    fn cells(&self, readers: io::Result<Vec<RunReader>>) -> Cells {
+        match readers {
+            Ok(readers) => Box::new(readers.into_iter().flatten()),
+            Err(e) => {
+                self.failed.record(e);
+                Box::new(std::iter::empty())
+            }
+        }
+    }
+}
+
+impl Storage for Spill {
+    fn insert(&mut self, item: Cell) {
+        let size = item.len() + OVERHEAD;
+        if self.mem.insert(item) {
+            self.mem_bytes += size;
+        }
+
+        if self.mem_bytes >= self.budget {
+            if let Err(e) = self.spill() {
+                self.failed.record(e);
+            }
+        }
+    }
+
+    fn commit(&mut self) -> usize {
+        match self.try_commit() {
+            Ok(new) => new,
+            Err(e) => {
+                self.failed.record(e);
+                0
+            }
+        }
+    }
+
+    fn take_fresh(&mut self) -> Cells {
+        let failed = &self.failed;
+        let readers = self.fresh.drain(..)
+            .map(|run| RunReader::open(run, failed))
+            .collect();
+
+        self.cells(readers)
+    }
+
+    fn fresh(&mut self) -> Cells {
+        let readers = self.fresh.iter()
+            .map(|run| RunReader::peek(run, &self.failed))
+            .collect();
+
+        self.cells(readers)
+    }
+
+    fn snapshot(&mut self) -> Cells {
+        self.commit();
+
+        let readers = self.all.iter()
+            .map(|run| RunReader::peek(run, &self.failed))
+            .collect();
+
+        self.cells(readers)
+    }
+
+    fn len(&self) -> usize {
+        self.all.as_ref().map(|run| run.len).unwrap_or(0)
+    }
+
+    fn drain(&mut self) -> Cells {
+        self.commit();
+        self.fresh.clear();
+
+        let failed = &self.failed;
+        let readers = self.all.take().into_iter()
+            .map(|run| RunReader::open(run, failed))
+            .collect();
+
+        self.cells(readers)
+    }
+
+    fn take_error(&mut self) -> Option<Error> {
+        self.failed.take().map(Error::Io)
+    }
+}
+
+/// A sorted file of unique candidates, removed once dropped
+#[derive(Debug)]
+struct Run {
+    path: PathBuf,
+    len: usize
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+struct RunWriter {
+    path: PathBuf,
+    out: BufWriter<File>,
+    len: usize
+}
+
+impl RunWriter {
+    fn create(path: PathBuf) -> io::Result<Self> {
+        Ok(Self {
+            out: BufWriter::new(File::create(&path)?),
+            path,
+            len: 0
+        })
+    }
+
+    fn write(&mut self, item: &Cell) -> io::Result<()> {
+        self.out.write_all(&(item.len() as u32).to_le_bytes())?;
+        self.out.write_all(item)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<Run> {
+        self.out.flush()?;
+        Ok(Run { path: self.path, len: self.len })
+    }
+}
+
+struct RunReader {
+    input: BufReader<File>,
+    failed: Failed,
+    _run: Option<Run>
+}
+
+impl RunReader {
+    /// reads the run, which is removed once the reader is dropped
+    fn open(run: Run, failed: &Failed) -> io::Result<Self> {
+        Ok(Self {
+            input: BufReader::new(File::open(&run.path)?),
+            failed: failed.clone(),
+            _run: Some(run)
+        })
+    }
+
+    /// reads the run, leaving it in place
+    fn peek(run: &Run, failed: &Failed) -> io::Result<Self> {
+        Ok(Self {
+            input: BufReader::new(File::open(&run.path)?),
+            failed: failed.clone(),
+            _run: None
+        })
+    }
+
+    fn next_item(&mut self) -> io::Result<Option<Cell>> {
+        let mut len = [0; 4];
+        match self.input.read_exact(&mut len) {
+            Ok(()) => {},
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e)
+        }
+
+        let mut item = Cell::from_elem(0, u32::from_le_bytes(len) as usize);
+        self.input.read_exact(&mut item)?;
+        Ok(Some(item))
+    }
+}
+
+impl Iterator for RunReader {
+    type Item = Cell;
+
+    /// stops at the first read which fails, recording why
+    fn next(&mut self) -> Option<Cell> {
+        self.next_item().unwrap_or_else(|e| {
+            self.failed.record(e);
+            None
+        })
+    }
+}
+
+/// k-way merge of sorted runs, yielding each candidate once
+struct Merge {
+    readers: Vec<RunReader>,
+    heap: BinaryHeap<Reverse<(Cell, usize)>>,
+    last: Option<Cell>,
+    started: bool
+}
+
+impl Merge {
+    fn new(readers: Vec<RunReader>) -> Self {
+        Self {
+            heap: BinaryHeap::with_capacity(readers.len()),
+            readers,
+            last: None,
+            started: false
+        }
+    }
+
+    fn next_item(&mut self) -> io::Result<Option<Cell>> {
+        if !self.started {
+            self.started = true;
+            for (idx, reader) in self.readers.iter_mut().enumerate() {
+                if let Some(item) = reader.next_item()? {
+                    self.heap.push(Reverse((item, idx)));
+                }
+            }
+        }
+
+        while let Some(Reverse((item, idx))) = self.heap.pop() {
+            if let Some(next) = self.readers[idx].next_item()? {
+                self.heap.push(Reverse((next, idx)));
+            }
+
+            if self.last.as_ref() != Some(&item) {
+                self.last = Some(item.clone());
+                return Ok(Some(item))
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Length, RuleCell, Rulebook,
+        patterns::ConstPattern,
+        scheduler::{Scheduler, SingleThread}
+    };
+
+    #[test]
+    fn behavior_spill() {
+        let rules: Rulebook = vec![
+            (b'a', RuleCell::from(&b"4@"[..])),
+            (b'e', RuleCell::from(&b"3"[..])),
+            (b's', RuleCell::from(&b"5$z"[..])),
+        ].into();
+
+        let mut memory = SingleThread::new();
+        let mut spill = SingleThread::with_storage(Spill::new(256).unwrap());
+        memory.push(Cell::from(&b"essays"[..]));
+        spill.push(Cell::from(&b"essays"[..]));
+
+        for _ in 0..6 {
+            memory.new_generation::<ConstPattern>(&rules);
+            spill.new_generation::<ConstPattern>(&rules);
+            assert_eq!(memory.length(), spill.length());
+        }
+
+        let memory: HashSet<Cell> = memory.into();
+        let spill: HashSet<Cell> = spill.into();
+        assert_eq!(memory, spill);
+    }
+
+    #[test]
+    fn len_counts_settled() {
+        let mut memory = crate::storage::Memory::new();
+        let mut spill = Spill::new(0).unwrap();
+        for item in [&b"one"[..], b"two", b"one"] {
+            memory.insert(Cell::from(item));
+            spill.insert(Cell::from(item));
+        }
+        assert_eq!((memory.len(), spill.len()), (0, 0));
+
+        assert_eq!((memory.commit(), spill.commit()), (2, 2));
+        assert_eq!((memory.len(), spill.len()), (2, 2));
+    }
+
+    #[test]
+    fn records_io_errors() {
+        let mut spill = Spill::new(0).unwrap();
+        spill.insert(Cell::from(&b"lost"[..]));
+        std::fs::remove_dir_all(spill.dir.path()).unwrap();
+
+        assert_eq!(spill.commit(), 0);
+        assert!(matches!(spill.take_error(), Some(Error::Io(_))));
+        assert!(spill.take_error().is_none());
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{Cell, Error};
+
+use std::hash::BuildHasher;
+
+use hashbrown::{
+    HashMap, HashSet,
+    hash_map::{DefaultHashBuilder, RawEntryMut}
+};
+
+pub use crate::{
+    spill::Spill,
+    bloom::Bloom
+};
+
+/// Candidates handed out by a storage
+pub type Cells = Box<dyn Iterator<Item=Cell> + Send>;
+
+/// Where a scheduler keeps the candidates it has found,
+/// and how it tells which of them are duplicates.
+///
+/// Candidates are inserted throughout a generation, and only
+/// settled once the generation commits, so a storage is free
+/// to deduplicate in batches.
+pub trait Storage {
+    /// offers a candidate, it's settled on the next `commit`
+    fn insert(&mut self, item: Cell);
+
+    /// settles every candidate inserted since the last commit,
+    /// the ones which turned out to be new become fresh.
+    /// returns how many were new
+    fn commit(&mut self) -> usize;
+
+    /// takes the fresh candidates, they remain held by the storage
+    fn take_fresh(&mut self) -> Cells;
+
+    /// copies the fresh candidates out, without taking them
+    fn fresh(&mut self) -> Cells;
+
+    /// copies every settled candidate out
+    fn snapshot(&mut self) -> Cells;
+
+    /// amount of candidates settled
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// takes every candidate held, leaving the storage empty
+    fn drain(&mut self) -> Cells;
+
+    /// the first error met since this was last asked, a storage
+    /// which failed to hold some candidates carries on without them
+    fn take_error(&mut self) -> Option<Error> {
+        None
+    }
+}
+
+/// Keeps every candidate in memory, once, in the order they were found.
+/// Duplicates are told apart through a table of indices into them.
+#[derive(Debug, Default)]
+pub struct Memory {
+    items: Vec<Cell>,
+    index: HashMap<usize, ()>,
+    hasher: DefaultHashBuilder,
+    /// `items[fresh..settled]` are fresh, `items[settled..]` pending
+    fresh: usize,
+    settled: usize
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl Storage for Memory {
+    fn insert(&mut self, item: Cell) {
+        let (items, hasher) = (&self.items, &self.hasher);
+        let hash = hasher.hash_one(&item);
+
+        let new = match self.index.raw_entry_mut().from_hash(hash, |&x| items[x] == item) {
+            RawEntryMut::Occupied(_) => false,
+            RawEntryMut::Vacant(entry) => {
+                entry.insert_with_hasher(hash, items.len(), (), |&x| hasher.hash_one(&items[x]));
+                true
+            }
+        };
+        if new {
+            self.items.push(item);
+        }
+    }
+
+    fn commit(&mut self) -> usize {
+        let new = self.items.len() - self.settled;
+        self.settled = self.items.len();
+        new
+    }
+
+    fn take_fresh(&mut self) -> Cells {
+        let fresh = self.fresh();
+        self.fresh = self.settled;
+        fresh
+    }
+
+    fn fresh(&mut self) -> Cells {
+        let fresh = self.items[self.fresh..self.settled].to_vec();
+        Box::new(fresh.into_iter())
+    }
+
+    fn snapshot(&mut self) -> Cells {
+        let settled = self.items[..self.settled].to_vec();
+        Box::new(settled.into_iter())
+    }
+
+    fn len(&self) -> usize {
+        self.settled
+    }
+
+    fn drain(&mut self) -> Cells {
+        self.index.clear();
+        self.fresh = 0;
+        self.settled = 0;
+        Box::new(std::mem::take(&mut self.items).into_iter())
+    }
+}
+
+impl From<Memory> for HashSet<Cell> {
+    fn from(x: Memory) -> HashSet<Cell> {
+        x.items.into_iter().collect()
+    }
+}
@@ -0,0 +1,183 @@
+/// Generation as an asynchronous stream, which runs
+/// on the runtime of whoever calls `generate` rather
+/// than the runtime kept by `TokioMutex`
+/// ---
+use crate::{
+    Cell,
+    Error,
+    Generator,
+    builder::{GeneratorBuilder, Schedules, Sink}
+};
+
+use std::{
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering}
+    },
+    task::{Context, Poll}
+};
+
+use futures_core::Stream;
+use tokio::{
+    sync::mpsc,
+    task::JoinHandle
+};
+
+/// Candidates of a run `generate` started, in the order `Run::run`
+/// hands them out. Dropping the stream closes its channel, the run
+/// is cancelled as soon as it fails to send the next candidate
+#[derive(Debug)]
+pub struct CellStream {
+    rx: mpsc::Receiver<Cell>,
+    task: JoinHandle<Result<(), Error>>,
+    generations: Arc<AtomicUsize>
+}
+
+impl CellStream {
+    pub async fn next(&mut self) -> Option<Cell> {
+        self.rx.recv().await
+    }
+
+    /// generations whose candidates have all been sent
+    pub fn generations(&self) -> usize {
+        self.generations.load(Ordering::SeqCst)
+    }
+
+    /// waits for the run to end, failing with whatever stopped it
+    pub async fn finish(self) -> Result<(), Error> {
+        drop(self.rx);
+        match self.task.await {
+            Ok(done) => done,
+            Err(e) => std::panic::resume_unwind(e.into_panic())
+        }
+    }
+}
+
+impl Stream for CellStream {
+    type Item = Cell;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Cell>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// Builds the run `builder` describes and spawns it onto the blocking
+/// pool of the current tokio runtime, at most `capacity` candidates
+/// are buffered before it waits on the consumer to catch up.
+///
+/// Panics if called outside of a tokio runtime
+pub fn generate(builder: GeneratorBuilder, capacity: usize) -> Result<CellStream, Error> {
+    let run = builder.build()?;
+    let (tx, rx) = mpsc::channel(capacity.max(1));
+    let generations = Arc::new(AtomicUsize::new(0));
+
+    let sender = Sender { tx, cancel: run.cancel_token(), generations: generations.clone() };
+    Ok(CellStream {
+        rx,
+        task: tokio::task::spawn_blocking(move || run.run(sender).map(drop)),
+        generations
+    })
+}
+
+/// sends every candidate as soon as it's emitted, so the run
+/// never gets further ahead than the channel allows
+struct Sender {
+    tx: mpsc::Sender<Cell>,
+    cancel: crate::CancelToken,
+    generations: Arc<AtomicUsize>
+}
+
+impl Sink for Sender {
+    fn item(&mut self, item: Cell) {
+        if self.tx.blocking_send(item).is_err() {
+            self.cancel.cancel();
+        }
+    }
+
+    fn generation(&mut self, _gen: &mut Generator<Schedules>) -> Result<(), Error> {
+        self.generations.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Pattern,
+        patterns::ConstPattern,
+        policy::{Class, Policy},
+        scheduler::{Scheduler, SingleThread},
+        testutil::{rulebook, leet as rules}
+    };
+    use hashbrown::HashSet;
+
+    fn builder() -> GeneratorBuilder {
+        GeneratorBuilder::new()
+            .preset(rules())
+            .pattern(Pattern::Const)
+            .seed(&b"password"[..])
+    }
+
+    #[tokio::test]
+    async fn behavior_stream() {
+        let mut single = SingleThread::new();
+        single.push(Cell::from(&b"password"[..]));
+        for _ in 0..8 {
+            single.new_generation::<ConstPattern>(&rules());
+        }
+        let expected: HashSet<Cell> = single.into();
+
+        let mut stream = generate(builder(), 4).unwrap();
+        let mut found = Vec::new();
+        while let Some(x) = stream.next().await {
+            found.push(x);
+        }
+        stream.finish().await.unwrap();
+
+        assert_eq!(found[0], Cell::from(&b"password"[..]));
+        assert_eq!(found.len(), expected.len());
+        assert_eq!(found.into_iter().collect::<HashSet<_>>(), expected);
+    }
+
+    #[tokio::test]
+    async fn follows_the_builder() {
+        let policy = Policy { require: vec![Class::Digit], ..Policy::default() };
+        let mut stream = generate(builder().policy(policy).max_candidates(3), 4).unwrap();
+        let mut found = Vec::new();
+        while let Some(x) = stream.next().await {
+            found.push(x);
+        }
+
+        assert_eq!(found.len(), 3);
+        assert!(found.iter().all(|x| x.iter().any(u8::is_ascii_digit)));
+        assert!(generate(GeneratorBuilder::new(), 4).is_err());
+    }
+
+    #[tokio::test]
+    async fn stops_when_closed() {
+        let mut stream = generate(builder(), 1).unwrap();
+        assert!(stream.next().await.is_some());
+
+        stream.rx.close();
+        (&mut stream.task).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn slow_consumer_bounds_buffering() {
+        let rules = rulebook(&[(b'a', b"bc")]);
+        let builder = GeneratorBuilder::new().preset(rules).pattern(Pattern::Const).seed(&b"aaaaaaaa"[..]);
+        let mut stream = generate(builder, 1).unwrap();
+
+        // the seed, all 16 of the first generation, and one of the next
+        for _ in 0..18 {
+            assert!(stream.next().await.is_some());
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        // the second generation waits on the consumer, rather
+        // than the run going on to the third
+        assert_eq!(stream.generations(), 1);
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{Cell, Generator, Length, Handler, RuleCell, Rulebook, Scheduler, Snapshot};
+
+/// rulebook out of `(from, replacements)` pairs
+pub fn rulebook(rules: &[(u8, &[u8])]) -> Rulebook {
+    rules.iter()
+        .map(|&(from, to)| (from, RuleCell::from(to)))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// leetspeak, where `a` chains through `4` onto `A`
+pub fn leet() -> Rulebook {
+    rulebook(&[
+        (b'a', b"4@"),
+        (b'4', b"A"),
+        (b's', b"5$"),
+        (b'o', b"0"),
+        (b'p', b"P"),
+    ])
+}
+
+/// chains longer than one hop, two of which are cycles
+pub fn chains() -> Rulebook {
+    "a:b\nb:c\nc:d\ns:5\n5:s\no:0\n0:O\nO:o".parse().unwrap()
+}
+
+/// emits what `gen` was seeded with, then each of the
+/// next `generations`, in the order they were emitted
+pub fn generations<H, T>(gen: &mut Generator<T>, generations: usize) -> Vec<Cell>
+where
+    H: Handler,
+    T: Scheduler + Snapshot + Length
+{
+    let mut found = Vec::new();
+    gen.emit(|x| found.push(x));
+    for _ in 0..generations {
+        gen.new_generation::<H>().unwrap();
+        gen.emit(|x| found.push(x));
+    }
+    found
+}
@@ -1,4 +1,3 @@
-use crate::RuleCell;
 use crate::patterns::RuleEntry;
 
 #[derive(Debug, PartialEq)]
@@ -35,34 +34,37 @@ impl Permutation {
         self.cell.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.cell.is_empty()
+    }
+
     /// checks if we should step again
     pub fn peek_next(&self) -> bool {
         match &self.rule {
-            RuleEntry::Single(_byte) => !self.rule_idx > 0,
-            RuleEntry::Multi(buf) => buf.len() >= self.rule_idx+1
+            RuleEntry::Single(_byte) => self.rule_idx == 0,
+            RuleEntry::Multi(buf) => buf.len() > self.rule_idx
         }
     }
 
-    pub fn commit<'s>(&'s mut self) -> Option<&'s crate::Cell> {
+    pub fn commit(&mut self) -> Option<&crate::Cell> {
         match &self.rule {
-            RuleEntry::Single(_byte) => {
-                //println!("rules: {}", *_byte as char);
+            RuleEntry::Single(byte) => {
+                //println!("rules: {}", *byte as char);
 
                 if self.rule_idx > 0 {
                     return None
                 }
+                self.cell[self.cell_idx] = *byte;
                 self.rule_idx = 1;
-                return Some(&self.cell)
+                Some(&self.cell)
             }
 
             RuleEntry::Multi(rules) => {
                 //println!("rules: {}", String::from_utf8_lossy(&rules));
-                if self.rule.len() >= self.rule_idx+1 {
-                    self.cell[self.cell_idx] = *rules.get(self.rule_idx).unwrap();
-                    self.rule_idx += 1;
-                    return Some(&self.cell)
-                }
-                return None
+                let byte = *rules.get(self.rule_idx)?;
+                self.cell[self.cell_idx] = byte;
+                self.rule_idx += 1;
+                Some(&self.cell)
             }
         }
     }
@@ -71,24 +73,24 @@ impl Permutation {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::Cell;
+    use crate::{Cell, RuleCell};
     use smallvec::SmallVec;
     
     #[test]
     fn behavior_commit() {
         let mut cell = Cell::new();
-        cell.extend(b"...".iter().map(|x| *x));
+        cell.extend(b"...".iter().copied());
         let rules = {
             let mut rules = RuleCell::new();
-            rules.extend(b"AB".iter().map(|x| *x));
+            rules.extend(b"AB".iter().copied());
             RuleEntry::Multi(rules)
         };
 
         let mut permutate = Permutation::new(cell, rules, 0);
         let mut buf = SmallVec::new();
         
-        for x in vec![b"A..", b"B.."] {
-            buf.extend(x.iter().map(|x| *x));
+        for x in [b"A..", b"B.."] {
+            buf.extend(x.iter().copied());
             assert_eq!(Some(&buf), permutate.commit());
             buf.clear()
         }
@@ -96,4 +98,15 @@ mod test {
         assert_eq!(None, permutate.commit());
         assert_eq!(None, permutate.commit());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn behavior_commit_single() {
+        let cell = Cell::from(&b"..."[..]);
+        let mut permutate = Permutation::new(cell, RuleEntry::Single(b'A'), 1);
+
+        assert!(permutate.peek_next());
+        assert_eq!(Some(&Cell::from(&b".A."[..])), permutate.commit());
+        assert!(!permutate.peek_next());
+        assert_eq!(None, permutate.commit());
+    }
+}
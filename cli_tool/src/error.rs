@@ -1,13 +1,48 @@
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum Error {
     BadInput(String),
+    /// what the library refused, and the file it was reading if any
+    Charsub(charsub::Error, Option<PathBuf>),
+    Io(std::io::Error),
+}
+
+impl Error {
+    /// exit code for the error, after sysexits.h
+    pub fn code(&self) -> i32 {
+        match self {
+            Error::BadInput(_) | Error::Charsub(charsub::Error::InvalidConfig(_), _) => 64,
+            Error::Charsub(charsub::Error::Io(_), _) | Error::Io(_) => 74,
+            Error::Charsub(charsub::Error::CapacityOverflow, _) => 71,
+            Error::Charsub(_, _) => 65,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(x: std::io::Error) -> Error {
+        Error::Io(x)
+    }
+}
+
+impl From<charsub::Error> for Error {
+    fn from(x: charsub::Error) -> Error {
+        match x {
+            charsub::Error::Io(e) => Error::Io(e),
+            e => Error::Charsub(e, None)
+        }
+    }
 }
 
 impl std::error::Error for Error {}
 impl std::fmt::Display for Error {
     fn fmt(&self, x: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(x, "{:?}", &self);
-        Ok(())
+        match self {
+            Error::BadInput(msg) => writeln!(x, "{}", msg),
+            Error::Charsub(e, Some(path)) => writeln!(x, "{} in {}", e, path.display()),
+            Error::Charsub(e, None) => writeln!(x, "{}", e),
+            Error::Io(e) => writeln!(x, "{}", e)
+        }
     }
 }
@@ -1,30 +1,16 @@
 use structopt::StructOpt;
+use std::sync::Arc;
 use crate::error::Error;
-use charsub::RuleCell;
+use charsub::{RuleCell, Rulebook, Merge, Order, closure::Closure, remote::Address, keyspace::Shard, crack::Algorithm, policy::{Class, Policy}, markov::{Model, Threshold}, builder::GeneratorBuilder, Include, Exclude};
 
-#[derive(Debug, Clone, Copy)]
-pub enum Pattern {
-    Modulo,
-    Const,
-}
-
-impl std::str::FromStr for Pattern {
-    type Err = Error;
-
-    fn from_str(x: &str) -> Result<Pattern, Self::Err> {
-        Ok(match x.to_ascii_lowercase().as_str() {
-            "const" => Pattern::Const,
-            "modulo" => Pattern::Modulo,
-            _ => return Err(Error::BadInput(format!("Expected pattern, got '{}'", x)))
-        })
-    }
-}
+pub use charsub::Pattern;
 
 #[derive(Debug)]
 pub enum SchedulerInput {
     ThreadPool,
     AsyncRuntime,
     SingleThread,
+    WorkStealing,
 }
 
 impl std::str::FromStr for SchedulerInput {
@@ -35,23 +21,383 @@ impl std::str::FromStr for SchedulerInput {
             "multithread" | "multi" => SchedulerInput::ThreadPool,
             "single" => SchedulerInput::SingleThread,
             "async" => SchedulerInput::AsyncRuntime,
+            "steal" | "parallel" => SchedulerInput::WorkStealing,
 
             _ => return Err(Error::BadInput(format!("Expected SchedulerInput, got '{}'", x)))
         })
     }
 }
 
+#[derive(Debug)]
+pub enum Dedup {
+    Exact,
+    Spill,
+    Bloom,
+}
+
+impl std::str::FromStr for Dedup {
+    type Err = Error;
+
+    fn from_str(x: &str) -> Result<Dedup, Self::Err> {
+        Ok(match x.to_ascii_lowercase().as_str() {
+            "exact" => Dedup::Exact,
+            "spill" => Dedup::Spill,
+            "bloom" => Dedup::Bloom,
+            _ => return Err(Error::BadInput(format!("Expected dedup strategy, got '{}'", x)))
+        })
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "charsub", about = "Permutates input based on rules")]
+pub enum Command {
+    /// Generate every candidate reachable from the input
+    Generate(Opt),
+    /// Hand chunks of a wordlist's keyspace out to `worker` processes
+    Serve(ServeOpt),
+    /// Generate chunks handed out by `serve`
+    Worker(WorkerOpt),
+    /// Hash every candidate and report those matching a list of hashes
+    Crack(CrackOpt),
+    /// Train a markov model on a corpus, for scoring candidates
+    Train(TrainOpt),
+    /// Learn rules from pairs of base words and the variants people made of them
+    Learn(LearnOpt),
+    /// Report which rules turn a wordlist into known passwords, and at what cost
+    #[structopt(alias="analyze")]
+    Analyse(AnalyseOpt),
+    /// Show whether and how a seed turns into a given string
+    Explain(ExplainOpt),
+    /// Generate as described by a job file, written in toml, json or yaml
+    Run(RunOpt),
+}
+
+#[derive(Debug, StructOpt)]
+pub struct RunOpt {
+    /// The job file, its format told by its extension
+    #[structopt(parse(from_os_str))]
+    pub job: std::path::PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ExplainOpt {
+    /// The word to start from
+    #[structopt(long)]
+    pub seed: String,
+
+    /// The string it should turn into
+    pub target: String,
+
+    #[structopt(short, long, default_value="modulo")]
+    pub pattern: Pattern,
+
+    /// Read rules from a file, one `from:to` per line like `learn` writes.
+    /// May be given more than once, files come before rules given as arguments
+    #[structopt(long, parse(from_os_str), number_of_values=1)]
+    pub rules_file: Vec<std::path::PathBuf>,
+
+    /// How to combine rules for the same character: union, override or strict
+    #[structopt(long, default_value="union")]
+    pub merge: Merge,
+
+    /// Substitute characters based on the notation of `from:to`, `\xHH` being the byte of hex value HH
+    #[structopt(parse(try_from_str=parse_rule))]
+    pub rules: Vec<(u8, RuleCell)>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct AnalyseOpt {
+    /// Seeds, one per line
+    #[structopt(short, long, parse(from_os_str))]
+    pub wordlist: std::path::PathBuf,
+
+    /// Known passwords, one per line
+    #[structopt(long, parse(from_os_str))]
+    pub known: std::path::PathBuf,
+
+    #[structopt(short, long, default_value="modulo")]
+    pub pattern: Pattern,
+
+    /// Read rules from a file, one `from:to` per line like `learn` writes.
+    /// May be given more than once, files come before rules given as arguments
+    #[structopt(long, parse(from_os_str), number_of_values=1)]
+    pub rules_file: Vec<std::path::PathBuf>,
+
+    /// How to combine rules for the same character: union, override or strict
+    #[structopt(long, default_value="union")]
+    pub merge: Merge,
+
+    /// Substitute characters based on the notation of `from:to`, `\xHH` being the byte of hex value HH
+    #[structopt(parse(try_from_str=parse_rule))]
+    pub rules: Vec<(u8, RuleCell)>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct LearnOpt {
+    /// Pairs of a base word and its variant, one pair per line
+    #[structopt(long, parse(from_os_str))]
+    pub pairs: std::path::PathBuf,
+
+    /// What separates a base word from its variant
+    #[structopt(long, default_value="\t")]
+    pub separator: String,
+
+    /// Leave out substitutions seen fewer times than this
+    #[structopt(long, default_value="1")]
+    pub min_count: u64,
+
+    /// Write the rules to a file instead of stdout
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: Option<std::path::PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct TrainOpt {
+    /// Words to learn from, one per line
+    #[structopt(long, parse(from_os_str))]
+    pub corpus: std::path::PathBuf,
+
+    /// Characters each one is predicted from
+    #[structopt(long, default_value="3")]
+    pub context: usize,
+
+    /// Where to save the model
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: std::path::PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct FilterOpt {
+    /// Only keep candidates satisfying the policy in this file,
+    /// made of `length MIN-MAX` and `require CLASS` lines
+    #[structopt(long, parse(from_os_str))]
+    pub policy: Option<std::path::PathBuf>,
+
+    /// Only keep candidates at least this long
+    #[structopt(long)]
+    pub min_length: Option<usize>,
+
+    /// Only keep candidates at most this long
+    #[structopt(long)]
+    pub max_length: Option<usize>,
+
+    /// Only keep candidates with a character of each class,
+    /// `lower`, `upper`, `digit` or `symbol` (e.g. `--require digit,symbol`)
+    #[structopt(long, number_of_values=1, use_delimiter=true)]
+    pub require: Vec<Class>,
+
+    /// Only keep candidates this regular expression matches, may be given more than once
+    #[structopt(long="match", number_of_values=1, parse(try_from_str=parse_regex))]
+    pub matches: Vec<regex::bytes::Regex>,
+
+    /// Drop candidates this regular expression matches, may be given more than once
+    #[structopt(long, number_of_values=1, parse(try_from_str=parse_regex))]
+    pub exclude: Vec<regex::bytes::Regex>,
+
+    /// Markov model made by `train`, scores candidates for `--order likelihood`
+    #[structopt(long, parse(from_os_str))]
+    pub model: Option<std::path::PathBuf>,
+
+    /// Drop candidates the model scores below this, in mean log2
+    /// probability per character (e.g. `-4.5`)
+    #[structopt(long, allow_hyphen_values=true)]
+    pub min_score: Option<f64>,
+}
+
+impl FilterOpt {
+    /// the policy file with the flags on top, if any were given
+    pub fn policy(&self) -> Result<Option<Policy>, Error> {
+        if self.policy.is_none() && self.min_length.is_none() && self.max_length.is_none() && self.require.is_empty() {
+            return Ok(None)
+        }
+
+        let mut policy = match &self.policy {
+            Some(path) => Policy::load(path)?,
+            None => Policy::default()
+        };
+        policy.min_len = self.min_length.unwrap_or(policy.min_len);
+        policy.max_len = self.max_length.unwrap_or(policy.max_len);
+        for class in &self.require {
+            policy.require(*class);
+        }
+        Ok(Some(policy))
+    }
+
+    /// candidates have to match every `--match`, and none of the `--exclude`s
+    pub fn apply(&self, mut builder: GeneratorBuilder) -> Result<GeneratorBuilder, Error> {
+        if let Some(policy) = self.policy()? {
+            builder = builder.policy(policy);
+        }
+        for regex in &self.matches {
+            builder = builder.filter(Include(regex.clone()));
+        }
+        for regex in &self.exclude {
+            builder = builder.filter(Exclude(regex.clone()));
+        }
+
+        match (&self.model, self.min_score) {
+            (Some(path), min) => {
+                let model = Arc::new(Model::load(path)?);
+                builder = builder.model(model.clone());
+                if let Some(min) = min {
+                    builder = builder.filter(Threshold { model, min });
+                }
+            },
+            (None, Some(_)) => return Err(Error::BadInput("--min-score needs a --model".into())),
+            (None, None) => {}
+        }
+        Ok(builder)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct CrackOpt {
+    /// Hashes to look for, one per line. Crypt's `$1$`, `$2b$`, `$5$` and `$6$`
+    /// and django's `pbkdf2_sha256$` are recognised, others are hex or `salt:hex`
+    #[structopt(long, parse(from_os_str))]
+    pub hashes: std::path::PathBuf,
+
+    /// Hash function hex and `salt:hex` hashes were made with,
+    /// `md5`, `sha1`, `sha256` or `ntlm`. Salts go before the candidate
+    #[structopt(long)]
+    pub algo: Option<Algorithm>,
+
+    #[structopt(short, long, default_value="modulo")]
+    pub pattern: Pattern,
+
+    /// How far rules carry the input's characters: `chained` substitutes what earlier
+    /// generations substituted, `single-hop` only the input's own characters and
+    /// `transitive` applies every chain of rules at once
+    #[structopt(long, default_value="chained")]
+    pub closure: Closure,
+
+    /// More seeds to try alongside the input, one per line
+    #[structopt(short, long, parse(from_os_str))]
+    pub wordlist: Option<std::path::PathBuf>,
+
+    #[structopt(short, default_value="steal")]
+    pub scheduler: SchedulerInput,
+
+    #[structopt(long, short = "-c", default_value="2", env="C_WORKERS")]
+    pub workers: usize,
+
+    /// Stop after this long, in seconds or suffixed with s, m, h or d (e.g. `90m`)
+    #[structopt(long, parse(try_from_str=parse_duration))]
+    pub max_time: Option<std::time::Duration>,
+
+    /// Write matches to a file instead of stdout
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: Option<std::path::PathBuf>,
+
+    #[structopt(flatten)]
+    pub filter: FilterOpt,
+
+    /// The root of value of characters are being manipulated.
+    #[structopt()]
+    pub input: String,
+
+    /// Read rules from a file, one `from:to` per line like `learn` writes.
+    /// May be given more than once, files come before rules given as arguments
+    #[structopt(long, parse(from_os_str), number_of_values=1)]
+    pub rules_file: Vec<std::path::PathBuf>,
+
+    /// How to combine rules for the same character: union, override or strict
+    #[structopt(long, default_value="union")]
+    pub merge: Merge,
+
+    /// Substitute characters based on the notation of `from:to`, `\xHH` being the byte of hex value HH
+    #[structopt(parse(try_from_str=parse_rule))]
+    pub rules: Vec<(u8, RuleCell)>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ServeOpt {
+    /// Address to listen on, `unix:PATH` or `HOST:PORT`
+    #[structopt(long)]
+    pub listen: Address,
+
+    #[structopt(short, long, default_value="modulo")]
+    pub pattern: Pattern,
+
+    /// How far rules carry the seeds' characters: `chained` substitutes what earlier
+    /// generations substituted, `single-hop` only the seeds' own characters and
+    /// `transitive` applies every chain of rules at once
+    #[structopt(long, default_value="chained")]
+    pub closure: Closure,
+
+    /// Seeds, one per line
+    #[structopt(short, long, parse(from_os_str))]
+    pub wordlist: std::path::PathBuf,
+
+    /// Candidates per chunk, at most 4294967295
+    #[structopt(long, default_value="65536", parse(try_from_str=parse_chunk))]
+    pub chunk: u128,
+
+    /// Seconds a worker may stay silent before its chunk is handed to another
+    #[structopt(long, default_value="600")]
+    pub timeout: u64,
+
+    /// Write candidates workers send back to a file instead of stdout
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: Option<std::path::PathBuf>,
+
+    /// Read rules from a file, one `from:to` per line like `learn` writes.
+    /// May be given more than once, files come before rules given as arguments
+    #[structopt(long, parse(from_os_str), number_of_values=1)]
+    pub rules_file: Vec<std::path::PathBuf>,
+
+    /// How to combine rules for the same character: union, override or strict
+    #[structopt(long, default_value="union")]
+    pub merge: Merge,
+
+    /// Substitute characters based on the notation of `from:to`, `\xHH` being the byte of hex value HH
+    #[structopt(parse(try_from_str=parse_rule))]
+    pub rules: Vec<(u8, RuleCell)>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct WorkerOpt {
+    /// Address of the coordinator, `unix:PATH` or `HOST:PORT`
+    #[structopt(long)]
+    pub connect: Address,
+
+    /// Write each chunk into this directory rather than sending it back
+    #[structopt(long, parse(from_os_str))]
+    pub shards: Option<std::path::PathBuf>,
+}
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "Permutates input based on rules", about = "An example of StructOpt usage.")]
 pub struct Opt {
     /// Describe which handler you'd like to use
     #[structopt(short, long, default_value="modulo")]
     pub pattern: Pattern,
 
-    /// limit the amount of times it regenerates 
-    #[structopt(short, long, default_value="0")]
-    pub limit: usize,
+    /// Order candidates are written in, `unordered`, `substitutions`, `lexicographic`,
+    /// `index` or `likelihood`. The last three are only written once the run is done
+    #[structopt(long, default_value="unordered", parse(try_from_str=parse_order))]
+    pub order: Order,
+
+    /// How far rules carry the input's characters: `chained` substitutes what earlier
+    /// generations substituted, `single-hop` only the input's own characters and
+    /// `transitive` applies every chain of rules at once
+    #[structopt(long, default_value="chained")]
+    pub closure: Closure,
+
+    /// Only write slice `i` of `n`, for splitting a run across machines by hand.
+    /// Every slice is enumerated straight from the keyspace, whichever the scheduler
+    #[structopt(long)]
+    pub shard: Option<Shard>,
+
+    /// Stop after this long, in seconds or suffixed with s, m, h or d (e.g. `90m`)
+    #[structopt(long, parse(try_from_str=parse_duration))]
+    pub max_time: Option<std::time::Duration>,
+
+    /// Stop after emitting this many candidates
+    #[structopt(long)]
+    pub max_candidates: Option<u64>,
+
+    #[structopt(flatten)]
+    pub filter: FilterOpt,
     
     #[structopt(short, default_value="single")]
     pub scheduler: SchedulerInput,
@@ -59,25 +405,127 @@ pub struct Opt {
     #[structopt(long, short = "-c", default_value="2", env="C_WORKERS")]
     pub workers: usize,
 
+    /// How the single threaded scheduler deduplicates candidates, either `exact` in memory,
+    /// `spill` to disk past `--memory-budget`, or `bloom` which may drop a few new candidates
+    #[structopt(long, default_value="exact")]
+    pub dedup: Dedup,
+
+    /// Megabytes of candidates held in memory before spilling to disk
+    #[structopt(long, default_value="256")]
+    pub memory_budget: usize,
+
+    /// Directory spilled candidates are written under, defaults to the system's temp directory
+    #[structopt(long, parse(from_os_str))]
+    pub spill_dir: Option<std::path::PathBuf>,
+
+    /// Amount of candidates the bloom filter is sized for
+    #[structopt(long, default_value="16777216")]
+    pub bloom_capacity: usize,
+
+    /// False positive rate the bloom filter is sized for
+    #[structopt(long, default_value="0.001")]
+    pub bloom_error: f64,
+
+    /// Write candidates to a file instead of stdout, when resuming it's cut back
+    /// to where it was at the checkpoint and appended to
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: Option<std::path::PathBuf>,
+
+    /// Periodically save progress to this file, so the run can be resumed
+    #[structopt(long, parse(from_os_str))]
+    pub checkpoint: Option<std::path::PathBuf>,
+
+    /// Seconds between checkpoints
+    #[structopt(long, default_value="300")]
+    pub checkpoint_every: u64,
+
+    /// Continue the run saved in this checkpoint, the input and rules must match it
+    #[structopt(long, parse(from_os_str))]
+    pub resume: Option<std::path::PathBuf>,
+
+    /// Seconds between progress reports on stderr, 0 turns them off
+    #[structopt(long, default_value="10")]
+    pub progress: u64,
+
+    /// Keep a JSON status file up to date with every progress report
+    #[structopt(long, parse(from_os_str))]
+    pub status: Option<std::path::PathBuf>,
+
+    /// Report what each generation found on stderr: new candidates,
+    /// duplicates and time spent
+    #[structopt(short, long)]
+    pub verbose: bool,
+
     /// The root of value of characters are being manipulated.
     #[structopt()]
     pub input: String,
 
-    /// Substitute characters based on the notation of `from:to` 
+    /// More seeds to try alongside the input, one per line
+    #[structopt(short, long, parse(from_os_str))]
+    pub wordlist: Option<std::path::PathBuf>,
+
+    /// More seeds to try alongside the input, as listed by a job file
+    #[structopt(skip)]
+    pub seeds: Vec<String>,
+
+    /// Read rules from a file, one `from:to` per line like `learn` writes.
+    /// May be given more than once, files come before rules given as arguments
+    #[structopt(long, parse(from_os_str), number_of_values=1)]
+    pub rules_file: Vec<std::path::PathBuf>,
+
+    /// How to combine rules for the same character: union, override or strict
+    #[structopt(long, default_value="union")]
+    pub merge: Merge,
+
+    /// Substitute characters based on the notation of `from:to`, `\xHH` being the byte of hex value HH
     /// where `to` can be any length, but `from` is restricted to any characer
     /// example: 1:ilLI a:b b:dp
     #[structopt(parse(try_from_str=parse_rule))]
     pub rules: Vec<(u8, RuleCell)>,
 }
 
-fn parse_rule(buf: &str) -> Result<(u8, RuleCell), Box<dyn std::error::Error>> {
-    const DELIMIER: char = ':';
-    let mut iter = buf.split(DELIMIER);
+fn parse_rule(buf: &str) -> Result<(u8, RuleCell), charsub::Error> {
+    Rulebook::parse_rule(buf)
+}
 
-    let root: u8 = iter.next().unwrap().as_bytes()[0];
-    
-    let mut rule_entry = RuleCell::new();
-    rule_entry.extend(iter.next().unwrap().bytes());
-    
-    Ok((root, rule_entry))
-}
\ No newline at end of file
+fn parse_regex(x: &str) -> Result<regex::bytes::Regex, Error> {
+    regex::bytes::Regex::new(x)
+        .map_err(|e| Error::BadInput(format!("Expected regular expression, got '{}': {}", x, e)))
+}
+
+pub fn parse_duration(buf: &str) -> Result<std::time::Duration, Error> {
+    let split = buf.find(|x: char| !x.is_ascii_digit()).unwrap_or(buf.len());
+    let (amount, unit) = buf.split_at(split);
+
+    let amount: u64 = amount.parse()
+        .map_err(|_| Error::BadInput(format!("Expected duration, got '{}'", buf)))?;
+
+    let scale = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(Error::BadInput(format!("Expected duration, got '{}'", buf)))
+    };
+    let secs = amount.checked_mul(scale)
+        .ok_or_else(|| Error::BadInput(format!("Expected a duration which fits in {} seconds, got '{}'", u64::MAX, buf)))?;
+    Ok(std::time::Duration::from_secs(secs))
+}
+
+fn parse_chunk(x: &str) -> Result<u128, Error> {
+    match x.parse() {
+        Ok(chunk) if chunk <= charsub::remote::MAX_CHUNK => Ok(chunk),
+        _ => Err(Error::BadInput(format!("Expected at most {} candidates per chunk, got '{}'", charsub::remote::MAX_CHUNK, x)))
+    }
+}
+
+fn parse_order(x: &str) -> Result<Order, Error> {
+    Ok(match x.to_ascii_lowercase().as_str() {
+        "unordered" | "none" => Order::Unordered,
+        "substitutions" | "subs" => Order::Substitutions,
+        "lexicographic" | "lex" => Order::Lexicographic,
+        "index" => Order::Index,
+        "likelihood" | "likely" => Order::Likelihood,
+        _ => return Err(Error::BadInput(format!("Expected order, got '{}'", x)))
+    })
+}
@@ -0,0 +1,315 @@
+use crate::{
+    error::Error,
+    input::{Opt, Pattern, SchedulerInput, Dedup, parse_duration}
+};
+
+use charsub::{
+    Order, Merge, Rulebook, RuleCell, Include, Exclude,
+    closure::Closure,
+    keyspace::Shard,
+    policy::Policy
+};
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration
+};
+
+use serde::{Deserialize, Deserializer, de::Error as _};
+use structopt::StructOpt;
+
+/// A `generate` run written down, so it can be kept and reviewed
+/// alongside whatever it's for. Paths are relative to the job file
+/// ```toml
+/// seeds = ["password"]
+/// presets = ["team.rules"]
+///
+/// [rules]
+/// a = "4@"
+/// s = "5$"
+///
+/// [filter.policy]
+/// min_len = 8
+/// require = ["digit"]
+///
+/// [limits]
+/// max_time = "90m"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Job {
+    /// the first is generate's input
+    pub seeds: Vec<String>,
+    /// more seeds, one per line
+    pub wordlist: Option<PathBuf>,
+    #[serde(deserialize_with = "parsed")]
+    pub pattern: Option<Pattern>,
+    pub order: Order,
+    pub closure: Closure,
+    /// rule files, merged in order before `rules`
+    pub presets: Vec<PathBuf>,
+    pub merge: Merge,
+    pub rules: Option<Rulebook>,
+    pub filter: FilterJob,
+    pub scheduler: SchedulerJob,
+    pub output: OutputJob,
+    pub limits: LimitsJob,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterJob {
+    pub policy: Option<Policy>,
+    /// a policy file, the `policy` table goes on top of it
+    pub policy_file: Option<PathBuf>,
+    #[serde(rename = "match")]
+    pub matches: Vec<Include>,
+    pub exclude: Vec<Exclude>,
+    pub model: Option<PathBuf>,
+    pub min_score: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulerJob {
+    #[serde(deserialize_with = "parsed")]
+    pub kind: Option<SchedulerInput>,
+    pub workers: Option<usize>,
+    #[serde(deserialize_with = "parsed")]
+    pub dedup: Option<Dedup>,
+    pub memory_budget: Option<usize>,
+    pub spill_dir: Option<PathBuf>,
+    pub bloom_capacity: Option<usize>,
+    pub bloom_error: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputJob {
+    pub path: Option<PathBuf>,
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_every: Option<u64>,
+    pub status: Option<PathBuf>,
+    pub progress: Option<u64>,
+    pub verbose: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsJob {
+    #[serde(deserialize_with = "duration")]
+    pub max_time: Option<Duration>,
+    pub max_candidates: Option<u64>,
+    pub shard: Option<Shard>,
+}
+
+impl Job {
+    /// reads a job as toml, json or yaml by the file's extension
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)?;
+        let bad = |e: &dyn Display| Error::BadInput(format!("Bad job file {}: {}", path.display(), e));
+
+        let mut job: Job = match path.extension().and_then(|x| x.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(|e| bad(&e))?,
+            Some("json") => serde_json::from_str(&text).map_err(|e| bad(&e))?,
+            Some("yaml" | "yml") => serde_yaml::from_str(&text).map_err(|e| bad(&e))?,
+            _ => return Err(Error::BadInput(format!("Expected a .toml, .json or .yaml job file, got {}", path.display())))
+        };
+
+        job.relative_to(path.parent().unwrap_or_else(|| Path::new(".")));
+        Ok(job)
+    }
+
+    /// the command line this job stands for
+    pub fn into_opt(self) -> Result<Opt, Error> {
+        let mut seeds = self.seeds.into_iter();
+        let input = seeds.next()
+            .ok_or_else(|| Error::BadInput("A job needs at least one seed under `seeds`".into()))?;
+
+        let mut opt = Opt::from_iter_safe(["generate", "--", input.as_str()])
+            .map_err(|e| Error::BadInput(e.message))?;
+        opt.seeds = seeds.collect();
+        opt.wordlist = self.wordlist;
+        opt.pattern = self.pattern.unwrap_or(opt.pattern);
+        opt.order = self.order;
+        opt.closure = self.closure;
+
+        opt.rules_file = self.presets;
+        opt.merge = self.merge;
+        let mut rules: Vec<(u8, RuleCell)> = self.rules.unwrap_or_else(|| Rulebook::from(Vec::new())).0
+            .into_iter()
+            .map(|(from, to)| (from, RuleCell::from(to)))
+            .collect();
+        rules.sort();
+        opt.rules = rules;
+
+        let filter = self.filter;
+        opt.filter.policy = filter.policy_file;
+        if let Some(policy) = filter.policy {
+            opt.filter.min_length = Some(policy.min_len);
+            opt.filter.max_length = Some(policy.max_len);
+            opt.filter.require = policy.require;
+        }
+        opt.filter.matches = filter.matches.into_iter().map(|x| x.0).collect();
+        opt.filter.exclude = filter.exclude.into_iter().map(|x| x.0).collect();
+        opt.filter.model = filter.model;
+        opt.filter.min_score = filter.min_score;
+
+        let scheduler = self.scheduler;
+        opt.scheduler = scheduler.kind.unwrap_or(opt.scheduler);
+        opt.workers = scheduler.workers.unwrap_or(opt.workers);
+        opt.dedup = scheduler.dedup.unwrap_or(opt.dedup);
+        opt.memory_budget = scheduler.memory_budget.unwrap_or(opt.memory_budget);
+        opt.spill_dir = scheduler.spill_dir;
+        opt.bloom_capacity = scheduler.bloom_capacity.unwrap_or(opt.bloom_capacity);
+        opt.bloom_error = scheduler.bloom_error.unwrap_or(opt.bloom_error);
+
+        let output = self.output;
+        opt.output = output.path;
+        opt.checkpoint = output.checkpoint;
+        opt.checkpoint_every = output.checkpoint_every.unwrap_or(opt.checkpoint_every);
+        opt.status = output.status;
+        opt.progress = output.progress.unwrap_or(opt.progress);
+        opt.verbose = output.verbose;
+
+        opt.max_time = self.limits.max_time;
+        opt.max_candidates = self.limits.max_candidates;
+        opt.shard = self.limits.shard;
+        Ok(opt)
+    }
+
+    fn relative_to(&mut self, dir: &Path) {
+        let paths = [
+            &mut self.wordlist,
+            &mut self.filter.policy_file,
+            &mut self.filter.model,
+            &mut self.scheduler.spill_dir,
+            &mut self.output.path,
+            &mut self.output.checkpoint,
+            &mut self.output.status,
+        ];
+        for path in IntoIterator::into_iter(paths).filter_map(Option::as_mut).chain(&mut self.presets) {
+            *path = dir.join(&*path);
+        }
+    }
+}
+
+/// reads a value through its `FromStr`, so it's named like on the command line
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|x| x.parse().map_err(|e: T::Err| D::Error::custom(e.to_string().trim_end())))
+        .transpose()
+}
+
+fn duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where D: Deserializer<'de>
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|x| parse_duration(&x).map_err(|e| D::Error::custom(e.to_string().trim_end())))
+        .transpose()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use charsub::policy::Class;
+
+    const TOML: &str = r#"
+seeds = ["password"]
+presets = ["team.rules"]
+
+[rules]
+a = "4@"
+s = "5$"
+
+[filter.policy]
+min_len = 8
+require = ["digit"]
+
+[limits]
+max_time = "90m"
+"#;
+
+    const JSON: &str = r#"{
+    "seeds": ["password"],
+    "presets": ["team.rules"],
+    "rules": {"a": "4@", "s": "5$"},
+    "filter": {"policy": {"min_len": 8, "require": ["digit"]}},
+    "limits": {"max_time": "90m"}
+}"#;
+
+    const YAML: &str = r#"
+seeds: [password]
+presets: [team.rules]
+rules:
+  a: "4@"
+  s: "5$"
+filter:
+  policy:
+    min_len: 8
+    require: [digit]
+limits:
+  max_time: 90m
+"#;
+
+    #[test]
+    fn behavior_job() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, text) in [("job.toml", TOML), ("job.json", JSON), ("job.yaml", YAML)] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, text).unwrap();
+            let job = Job::load(&path).unwrap();
+
+            assert_eq!(job.seeds, vec!["password".to_string()], "{}", name);
+            assert_eq!(job.presets, vec![dir.path().join("team.rules")], "{}", name);
+            let rules = job.rules.as_ref().unwrap();
+            assert_eq!(rules.alternatives(b'a').map(|x| x.to_vec()), Some(b"4@".to_vec()), "{}", name);
+            assert_eq!(rules.alternatives(b's').map(|x| x.to_vec()), Some(b"5$".to_vec()), "{}", name);
+            let policy = job.filter.policy.as_ref().unwrap();
+            assert_eq!((policy.min_len, &policy.require), (8, &vec![Class::Digit]), "{}", name);
+            assert_eq!(job.limits.max_time, Some(Duration::from_secs(90 * 60)), "{}", name);
+
+            let opt = job.into_opt().unwrap();
+            assert_eq!(opt.input, "password");
+            assert_eq!(opt.rules_file, vec![dir.path().join("team.rules")]);
+            assert_eq!(opt.rules, vec![(b'a', RuleCell::from(&b"4@"[..])), (b's', RuleCell::from(&b"5$"[..]))]);
+        }
+
+        assert!(Job::load(&dir.path().join("job.ini")).is_err());
+    }
+
+    #[test]
+    fn relative_paths() {
+        let mut job: Job = toml::from_str(r#"
+wordlist = "words.txt"
+presets = ["team.rules", "/etc/charsub/base.rules"]
+
+[output]
+path = "out/found.txt"
+checkpoint = "state.ckpt"
+"#).unwrap();
+
+        job.relative_to(Path::new("jobs"));
+        assert_eq!(job.wordlist, Some(PathBuf::from("jobs/words.txt")));
+        assert_eq!(job.presets, vec![PathBuf::from("jobs/team.rules"), PathBuf::from("/etc/charsub/base.rules")]);
+        assert_eq!(job.output.path, Some(PathBuf::from("jobs/out/found.txt")));
+        assert_eq!(job.output.checkpoint, Some(PathBuf::from("jobs/state.ckpt")));
+        assert_eq!(job.output.status, None);
+    }
+
+    #[test]
+    fn unknown_fields() {
+        assert!(toml::from_str::<Job>("seed = [\"password\"]").is_err());
+        assert!(toml::from_str::<Job>("[limits]\nmax_tiem = \"1h\"").is_err());
+        assert!(serde_json::from_str::<Job>(r#"{"filter": {"polcy": {}}}"#).is_err());
+        assert!(serde_yaml::from_str::<Job>("output:\n  verbosity: true\n").is_err());
+        assert!(toml::from_str::<Job>("seeds = [\"password\"]").is_ok());
+    }
+}
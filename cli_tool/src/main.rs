@@ -1,58 +1,591 @@
 mod multi_runtime;
 mod input;
 mod error;
+mod progress;
+mod job;
 
 use charsub::{
     Generator,
     ModulusPattern,
     ConstPattern,
     Handler,
-    Permutation
+    Permutation,
+    Cell,
+    CancelToken,
+    Rulebook,
+    RuleCell,
+    Merge,
+    checkpoint::Checkpoint,
+    crack::Targets,
+    markov::Model,
+    learn::Learner,
+    analyse,
+    explain,
+    builder::{GeneratorBuilder, Sink},
+    remote::{self, Job, Listener, ServeOptions}
 };
 
+#[allow(dead_code)]
 #[derive(Debug, Default)]
 struct Binary;
 impl Handler for Binary {
     fn handle(permute: &Permutation) -> bool {
-        ModulusPattern::handle(permute) || ConstPattern::handle(permute) 
+        ModulusPattern::handle(permute) || ConstPattern::handle(permute)
     }
 }
 
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    time::{Duration, Instant}
+};
+
+use hashbrown::HashSet;
 use structopt::StructOpt;
-use crate::input::Pattern;
+use crate::{
+    input::{Command, Opt, ServeOpt, WorkerOpt, CrackOpt, TrainOpt, LearnOpt, AnalyseOpt, ExplainOpt, Pattern},
+    multi_runtime::Schedules,
+    error::Error
+};
 
 fn main() {
-    let opt = input::Opt::from_args();
-    let pattern = opt.pattern;
-    
-    println!("{:#?}", &opt);
-
-    let mut gen = Generator::new(
-        opt.rules.clone(),
-        multi_runtime::Schedules::from_opt(&opt)
-    );
-    gen.seed(opt.input.as_bytes());
-
-    println!("{:#?}", &gen);
-    let mut generation = 0;
-
-    loop {
-        let last = gen.length();
-        match pattern {
-            Pattern::Const => gen.new_generation::<ConstPattern>(),
-            Pattern::Modulo => gen.new_generation::<ModulusPattern>(),
+    let result = match Command::from_args() {
+        Command::Generate(opt) => generate(opt),
+        Command::Serve(opt) => serve(opt),
+        Command::Worker(opt) => worker(opt),
+        Command::Crack(opt) => crack(opt),
+        Command::Train(opt) => train(opt),
+        Command::Learn(opt) => learn(opt),
+        Command::Analyse(opt) => analyse(opt),
+        Command::Explain(opt) => explain(opt),
+        Command::Run(opt) => job::Job::load(&opt.job).and_then(job::Job::into_opt).and_then(generate),
+    };
+
+    if let Err(e) = result {
+        eprint!("{}", e);
+        std::process::exit(e.code())
+    }
+}
+
+fn generate(opt: Opt) -> Result<(), Error> {
+    let pattern_name = opt.pattern.name();
+
+    if opt.verbose {
+        eprintln!("{:#?}", &opt);
+    }
+
+    if opt.order.is_buffered() && (opt.checkpoint.is_some() || opt.resume.is_some()) {
+        return Err(Error::BadInput(format!("--order {:?} only writes once the run is done, so it can't be checkpointed", opt.order).to_lowercase()))
+    }
+    if opt.shard.is_some() && (opt.checkpoint.is_some() || opt.resume.is_some()) {
+        return Err(Error::BadInput("--shard can't be checkpointed".into()))
+    }
+
+    let cancel = CancelToken::new();
+    watch_interrupt(cancel.clone());
+
+    let mut seeds = vec![Cell::from(opt.input.as_bytes())];
+    seeds.extend(opt.seeds.iter().map(|x| Cell::from(x.as_bytes())));
+    if let Some(path) = &opt.wordlist {
+        seeds.extend(read_words(path)?);
+    }
+
+    let mut builder = GeneratorBuilder::new()
+        .preset(rulebook(&opt.rules_file, &opt.rules, opt.merge)?)
+        .scheduler(multi_runtime::from_opt(&opt)?)
+        .closure(opt.closure)
+        .pattern(opt.pattern)
+        .order(opt.order)
+        .seeds(seeds.clone())
+        .cancel(cancel);
+    builder = opt.filter.apply(builder)?;
+    if let Some(max) = opt.max_time {
+        builder = builder.max_time(max);
+    }
+    if let Some(max) = opt.max_candidates {
+        builder = builder.max_candidates(max);
+    }
+    if let Some(shard) = opt.shard {
+        builder = builder.shard(shard);
+    }
+
+    let mut resumed = None;
+    let run = builder.build_with(|gen, seeds| -> Result<(), Error> {
+        let path = match &opt.resume {
+            Some(path) => path,
+            None => {
+                seeds.into_iter().for_each(|x| gen.seed(x));
+                return Ok(())
+            }
+        };
+
+        let (mut checkpoint, candidates) = Checkpoint::read(path)?;
+        if checkpoint.rules != gen.map().fingerprint() {
+            return Err(Error::BadInput("checkpoint was made with different rules".into()))
+        }
+        if checkpoint.pattern != pattern_name {
+            return Err(Error::BadInput(format!("checkpoint was made with the '{}' pattern", checkpoint.pattern)))
+        }
+        if checkpoint.closure != opt.closure {
+            return Err(Error::BadInput(format!("checkpoint was made with --closure {}", checkpoint.closure.name())))
+        }
+        if checkpoint.seeds != seeds {
+            return Err(Error::BadInput("checkpoint was made with a different input".into()))
+        }
+
+        gen.set_generation(checkpoint.generation);
+        let mut failed = None;
+        gen.resume(
+            checkpoint.seeds.clone(),
+            std::mem::take(&mut checkpoint.pending),
+            candidates.map_while(|x| x.map_err(|e| failed = Some(e)).ok())
+        );
+        if let Some(e) = failed {
+            return Err(e.into())
+        }
+        resumed = Some(checkpoint);
+        Ok(())
+    })?;
+
+    let stdout = io::stdout();
+    let written = resumed.as_ref().map_or(0, |x| x.output);
+    let out = BufWriter::new(Counted::new(match (&opt.output, &resumed) {
+        (Some(path), Some(checkpoint)) => Box::new(checkpoint.reopen_output(path)?) as Box<dyn Write>,
+        (Some(path), None) => Box::new(File::create(path)?),
+        (None, _) => Box::new(stdout.lock())
+    }, written));
+
+    let mut output = Output::new(out, &opt, &seeds);
+    let mut gen = run.run(&mut output)?;
+    if let Some(e) = output.failed.take() {
+        return Err(e.into())
+    }
+    output.out.flush()?;
+
+    if let Some(shard) = opt.shard {
+        eprintln!("Shard {}/{}: {} candidates", shard.index(), shard.count(), output.candidates);
+        return Ok(())
+    }
+
+    output.save(&mut gen)?;
+    output.report(&gen)?;
+
+    eprintln!("Generations: {}", gen.generation());
+    if output.candidates >= opt.max_candidates.unwrap_or(u64::MAX) {
+        eprintln!("Stopped after {} candidates", output.candidates);
+    }
+    else if gen.is_cancelled() {
+        match &opt.checkpoint {
+            Some(path) => eprintln!("Stopped early, resume from {} to carry on", path.display()),
+            None => eprintln!("Stopped early")
+        }
+    }
+
+    if let Schedules::Bloom(bloom) = gen.scheduler() {
+        let bloom = bloom.storage();
+        eprintln!("Duplicates dropped: {} (false positive rate ~{:.2e})", bloom.dropped(), bloom.false_positive_rate());
+    }
+
+    Ok(())
+}
+
+fn serve(opt: ServeOpt) -> Result<(), Error> {
+    let seeds = read_words(&opt.wordlist)?;
+
+    let rules = rulebook(&opt.rules_file, &opt.rules, opt.merge)?;
+    let mut job = Job::new(opt.pattern.name(), &rules, seeds);
+    job.closure = opt.closure;
+    let options = ServeOptions {
+        chunk: opt.chunk,
+        timeout: Some(Duration::from_secs(opt.timeout)).filter(|x| !x.is_zero())
+    };
+
+    let listener = Listener::bind(&opt.listen)?;
+    eprintln!("Serving {} seeds on {:?}", job.seeds.len(), listener.address()?);
+
+    let stdout = io::stdout();
+    let mut out: BufWriter<Box<dyn Write>> = BufWriter::new(match &opt.output {
+        Some(path) => Box::new(std::fs::File::create(path)?),
+        None => Box::new(stdout.lock())
+    });
+
+    let mut result = Ok(());
+    let stats = remote::serve(&listener, &job, options, |x| if result.is_ok() {
+        result = out.write_all(&x).and_then(|_| out.write_all(b"\n"));
+    })?;
+    result?;
+    out.flush()?;
+
+    eprintln!("Chunks: {} ({} reassigned) across {} workers", stats.chunks, stats.reassigned, stats.workers);
+    Ok(())
+}
+
+fn worker(opt: WorkerOpt) -> Result<(), Error> {
+    let conn = remote::connect(&opt.connect)?;
+    let finished = remote::work(conn, opt.shards.as_deref())?;
+    eprintln!("Chunks finished: {}", finished);
+    Ok(())
+}
+
+fn crack(opt: CrackOpt) -> Result<(), Error> {
+    let targets = Targets::load(opt.algo, &opt.hashes)?;
+    let total = targets.len();
+    eprintln!("Looking for {} hashes under {} salts", total, targets.salts());
+
+    let cancel = CancelToken::new();
+    watch_interrupt(cancel.clone());
+
+    let mut builder = GeneratorBuilder::new()
+        .preset(rulebook(&opt.rules_file, &opt.rules, opt.merge)?)
+        .scheduler(multi_runtime::schedules(&opt.scheduler, opt.workers)?)
+        .closure(opt.closure)
+        .pattern(opt.pattern)
+        .seed(opt.input.as_bytes())
+        .cancel(cancel.clone());
+    if let Some(path) = &opt.wordlist {
+        builder = builder.seeds(read_words(path)?);
+    }
+    if let Some(budget) = opt.max_time {
+        builder = builder.max_time(budget);
+    }
+    let run = opt.filter.apply(builder)?.build()?;
+
+    let stdout = io::stdout();
+    let out: BufWriter<Box<dyn Write>> = BufWriter::new(match &opt.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(stdout.lock())
+    });
+
+    let mut cracker = Cracker { targets, batch: Vec::new(), found: Vec::new(), hashed: 0, cancel, out };
+    let gen = run.run(&mut cracker)?;
+    cracker.check();
+    cracker.write(&gen)?;
+
+    eprintln!("Generations: {}", gen.generation());
+    eprintln!("Cracked {} of {} hashes from {} candidates", total - cracker.targets.len(), total, cracker.hashed);
+    if !cracker.targets.is_empty() && cracker.cancel.is_cancelled() {
+        eprintln!("Stopped early");
+    }
+    Ok(())
+}
+
+/// Hashes candidates in batches as the run hands them out,
+/// cancelling it once every target is found
+struct Cracker<W> {
+    targets: Targets,
+    batch: Vec<Cell>,
+    /// candidates and the hash they matched, yet to be written
+    found: Vec<(Cell, String)>,
+    hashed: u64,
+    cancel: CancelToken,
+    out: W,
+}
+
+impl<W: Write> Cracker<W> {
+    /// candidates hashed at once
+    const BATCH: usize = 1 << 16;
+
+    fn check(&mut self) {
+        self.hashed += self.batch.len() as u64;
+        for found in self.targets.check_all(&self.batch) {
+            self.targets.remove(&found.hash);
+            self.found.push((found.candidate, found.hash));
+        }
+        self.batch.clear();
+
+        if self.targets.is_empty() {
+            self.cancel.cancel();
+        }
+    }
+
+    /// seed, candidate and hash, tab separated
+    fn write(&mut self, gen: &Generator<Schedules>) -> io::Result<()> {
+        for (candidate, hash) in self.found.drain(..) {
+            self.out.write_all(gen.origin(&candidate).map(|x| x.as_slice()).unwrap_or_default())?;
+            self.out.write_all(b"\t")?;
+            self.out.write_all(&candidate)?;
+            writeln!(self.out, "\t{}", hash)?;
+        }
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Sink for &mut Cracker<W> {
+    /// whatever's handed out once the run is cancelled goes unhashed
+    fn item(&mut self, item: Cell) {
+        if self.cancel.is_cancelled() {
+            return
+        }
+        self.batch.push(item);
+        if self.batch.len() >= Cracker::<W>::BATCH {
+            self.check();
+        }
+    }
+
+    fn generation(&mut self, gen: &mut Generator<Schedules>) -> Result<(), charsub::Error> {
+        self.check();
+        Ok(self.write(gen)?)
+    }
+}
+
+fn train(opt: TrainOpt) -> Result<(), Error> {
+    let model = Model::train(opt.context, read_words(&opt.corpus)?);
+    model.save(&opt.output)?;
+    eprintln!("Saved a model of {} characters of context to {}", model.order(), opt.output.display());
+    Ok(())
+}
+
+fn learn(opt: LearnOpt) -> Result<(), Error> {
+    let pairs = std::fs::read(&opt.pairs)?;
+    let separator = opt.separator.as_bytes();
+    if separator.is_empty() {
+        return Err(Error::BadInput("--separator can't be empty".into()))
+    }
+
+    let mut learner = Learner::new();
+    for line in pairs.split(|x| *x == b'\n').map(|x| x.strip_suffix(b"\r").unwrap_or(x)) {
+        if let Some(split) = line.windows(separator.len()).position(|x| x == separator) {
+            learner.observe(&line[..split], &line[split + separator.len()..]);
+        }
+    }
+
+    match &opt.output {
+        Some(path) => learner.write(BufWriter::new(std::fs::File::create(path)?), opt.min_count)?,
+        None => learner.write(io::stdout().lock(), opt.min_count)?
+    }
+    eprintln!("Learned from {} pairs, skipped {} which differ in length", learner.pairs(), learner.skipped());
+    Ok(())
+}
+
+fn analyse(opt: AnalyseOpt) -> Result<(), Error> {
+    let seeds = read_words(&opt.wordlist)?;
+    let known: HashSet<Cell> = read_words(&opt.known)?.into_iter().collect();
+    let rules = rulebook(&opt.rules_file, &opt.rules, opt.merge)?;
+
+    let report = match opt.pattern {
+        Pattern::Const => analyse::analyse::<ConstPattern>(&seeds, &rules, &known),
+        Pattern::Modulo => analyse::analyse::<ModulusPattern>(&seeds, &rules, &known),
+    };
+    print!("{}", report);
+    Ok(())
+}
+
+fn explain(opt: ExplainOpt) -> Result<(), Error> {
+    let rules = rulebook(&opt.rules_file, &opt.rules, opt.merge)?;
+    let (seed, target) = (opt.seed.as_bytes(), opt.target.as_bytes());
+
+    let explanation = match opt.pattern {
+        Pattern::Const => explain::explain::<ConstPattern>(seed, target, &rules),
+        Pattern::Modulo => explain::explain::<ModulusPattern>(seed, target, &rules),
+    };
+    print!("{}", explanation);
+    Ok(())
+}
+
+/// where `generate` writes candidates, reporting progress
+/// and saving checkpoints between generations
+struct Output<'a> {
+    out: BufWriter<Counted<Box<dyn Write>>>,
+    /// the first write which failed, the run stops once its generation is done
+    failed: Option<io::Error>,
+    candidates: u64,
+    opt: &'a Opt,
+    seeds: &'a [Cell],
+    last_report: Instant,
+    last_checkpoint: Instant,
+}
+
+impl<'a> Output<'a> {
+    fn new(out: BufWriter<Counted<Box<dyn Write>>>, opt: &'a Opt, seeds: &'a [Cell]) -> Self {
+        Self {
+            out,
+            failed: None,
+            candidates: 0,
+            opt,
+            seeds,
+            last_report: Instant::now(),
+            last_checkpoint: Instant::now(),
+        }
+    }
+
+    fn save(&mut self, gen: &mut Generator<Schedules>) -> io::Result<()> {
+        let path = match &self.opt.checkpoint {
+            Some(path) => path,
+            None => return Ok(())
         };
-        
-        if gen.length()-last == 0 && gen.done() { break }    
-        if opt.limit != 0 && gen.generation() >= opt.limit { break }
 
-        generation += 1;
+        self.out.flush()?;
+        Checkpoint {
+            rules: gen.map().fingerprint(),
+            pattern: self.opt.pattern.name().to_string(),
+            closure: self.opt.closure,
+            generation: gen.generation(),
+            seeds: self.seeds.to_vec(),
+            pending: gen.unemitted().collect(),
+            output: self.out.get_ref().written
+        }.write(path, gen.snapshot())
+    }
+
+    fn report(&self, gen: &Generator<Schedules>) -> io::Result<()> {
+        let progress = gen.progress();
+        if self.opt.progress != 0 {
+            eprintln!("generation {}: {}", gen.generation(), progress);
+        }
+        match &self.opt.status {
+            Some(path) => progress::write_status(path, &progress, gen.generation()),
+            None => Ok(())
+        }
+    }
+}
+
+impl Sink for &mut Output<'_> {
+    fn item(&mut self, item: Cell) {
+        if self.failed.is_none() {
+            self.failed = self.out.write_all(&item).and_then(|_| self.out.write_all(b"\n")).err();
+        }
+        self.candidates += 1;
+    }
+
+    fn generation(&mut self, gen: &mut Generator<Schedules>) -> Result<(), charsub::Error> {
+        if let Some(e) = self.failed.take() {
+            return Err(e.into())
+        }
+        if let (true, Some(stats)) = (self.opt.verbose, gen.stats().last()) {
+            eprintln!("{}", stats);
+        }
+
+        let opt = self.opt;
+        if (opt.progress != 0 || opt.status.is_some()) && self.last_report.elapsed() >= Duration::from_secs(opt.progress) {
+            self.report(gen)?;
+            self.last_report = Instant::now();
+        }
+        if self.last_checkpoint.elapsed() >= Duration::from_secs(opt.checkpoint_every) {
+            self.save(gen)?;
+            self.last_checkpoint = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+/// counts the bytes written through it, on top of those already `written`
+struct Counted<W> {
+    inner: W,
+    written: u64
+}
+
+impl<W> Counted<W> {
+    fn new(inner: W, written: u64) -> Self {
+        Self { inner, written }
+    }
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// every non-empty line of `path`
+fn read_words(path: &std::path::Path) -> io::Result<Vec<Cell>> {
+    let words = std::fs::read(path)?;
+    Ok(words.split(|x| *x == b'\n')
+        .map(|x| x.strip_suffix(b"\r").unwrap_or(x))
+        .filter(|x| !x.is_empty())
+        .map(Cell::from)
+        .collect())
+}
+
+/// the rules in `files`, then those given as arguments, combined as `merge` says
+fn rulebook(files: &[std::path::PathBuf], rules: &[(u8, RuleCell)], merge: Merge) -> Result<Rulebook, Error> {
+    let mut rulebook = Rulebook::from(Vec::new());
+    for path in files {
+        Rulebook::load(path)
+            .and_then(|x| Ok(rulebook.merge(&x, merge)?))
+            .map_err(|e| Error::Charsub(e, Some(path.clone())))?;
     }
+    for (from, to) in rules {
+        rulebook.add(*from, to.clone(), merge)
+            .map_err(charsub::Error::from)?;
+    }
+
+    for cycle in rulebook.cycles() {
+        let chain: Vec<String> = cycle.iter()
+            .chain(cycle.first())
+            .map(|x| std::ascii::escape_default(*x).to_string())
+            .collect();
+        eprintln!("Warning: rules form a cycle, {}", chain.join(" -> "));
+    }
+    Ok(rulebook)
+}
 
-    println!("Generations: {}", generation);
-    
-    for x in gen.into_set() {
-        println!("{}", String::from_utf8_lossy(&x))
+/// the first ctrl-c stops the run cleanly, a second one exits right away
+fn watch_interrupt(cancel: CancelToken) {
+    let result = ctrlc::set_handler(move || {
+        if cancel.is_cancelled() {
+            std::process::exit(130)
+        }
+        eprintln!("Interrupted, finishing up");
+        cancel.cancel();
+    });
+
+    if let Err(e) = result {
+        eprintln!("Ctrl-C won't stop the run cleanly: {}", e);
     }
 }
 
+#[cfg(test)]
+mod test {
+    use super::*;
+    use charsub::{
+        crack::Algorithm,
+        scheduler::SingleThread,
+        storage::Spill
+    };
+
+    fn cracker(hashes: &[&str], cancel: CancelToken) -> Cracker<Vec<u8>> {
+        let targets = Targets::new(Some(Algorithm::Md5), hashes.iter().copied()).unwrap();
+        Cracker { targets, batch: Vec::new(), found: Vec::new(), hashed: 0, cancel, out: Vec::new() }
+    }
+
+    fn builder(cancel: CancelToken) -> GeneratorBuilder {
+        GeneratorBuilder::new()
+            .preset("a:4@\ns:5".parse().unwrap())
+            .seed(&b"pass"[..])
+            .cancel(cancel)
+    }
+
+    #[test]
+    fn crack_stops_with_the_run() {
+        // md5 of "p4s5"
+        let cancel = CancelToken::new();
+        let mut found = cracker(&["19112c0741c6f03c9a5018a2c3cae777"], cancel.clone());
+        let gen = builder(cancel).build().unwrap().run(&mut found).unwrap();
+        assert!(found.targets.is_empty());
+        assert_eq!(found.out, b"pass\tp4s5\t19112c0741c6f03c9a5018a2c3cae777\n");
+        assert_eq!(gen.generation(), 2);
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let mut cancelled = cracker(&["19112c0741c6f03c9a5018a2c3cae777"], cancel.clone());
+        builder(cancel).build().unwrap().run(&mut cancelled).unwrap();
+        assert_eq!(cancelled.hashed, 0);
+    }
+
+    #[test]
+    fn crack_fails_with_the_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let spill = Spill::new_in(0, dir.path()).unwrap();
+        std::fs::remove_dir_all(dir.path()).unwrap();
+
+        let cancel = CancelToken::new();
+        let mut cracker = cracker(&["19112c0741c6f03c9a5018a2c3cae777"], cancel.clone());
+        let run = builder(cancel).scheduler(Schedules::Spill(SingleThread::with_storage(spill))).build().unwrap();
+        assert!(matches!(run.run(&mut cracker), Err(charsub::Error::Io(_))));
+    }
+}
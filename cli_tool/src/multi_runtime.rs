@@ -3,94 +3,44 @@
 /// selectively during runtime
 /// ---
 use charsub::{
-    Scheduler,
-    scheduler::{MultithreadMutex, SingleThread, TokioMutex},
-    Permutation,
-    Cell, Handler,
-    Length, Rulebook
+    scheduler::{MultithreadMutex, SingleThread, TokioMutex, WorkStealing},
+    builder::Dedup as Storage
 };
 
-use crate::input::{
-    SchedulerInput,
-    Opt
-};
-
-use hashbrown::HashSet;
+pub use charsub::builder::Schedules;
 
-use std::{
-    sync::{Arc, Mutex},
+use crate::{
+    input::{SchedulerInput, Dedup, Opt},
+    error::Error
 };
 
-#[derive(Debug)]
-pub enum Schedules {
-    Async(TokioMutex),
-    Pool(MultithreadMutex),
-    SingleThread(SingleThread),
-}
-
-impl Schedules {
-    pub fn from_opt(opts: &Opt) -> Self {
-        match opts.scheduler {
-            SchedulerInput::AsyncRuntime => Schedules::Async(
-                TokioMutex::new()
-            ),
-            SchedulerInput::ThreadPool => Schedules::Pool(MultithreadMutex::new(opts.workers)),
-            SchedulerInput::SingleThread => Schedules::SingleThread(SingleThread::new())
-        }
-    }
-}
-
-impl Scheduler for Schedules { 
-    
-    fn push(&mut self, item: Cell) {
-        match self {
-            Schedules::Async(rt) => rt.push(item),
-            Schedules::Pool(pool) => pool.push(item),
-            Schedules::SingleThread(func) => func.push(item)
-        }
-    }
-
-    fn new_generation<H>(&mut self, rules: &Rulebook) where H: Handler {
-        match self {
-            Schedules::Async(rt) => rt.new_generation::<H>(rules),
-            Schedules::Pool(pool) => pool.new_generation::<H>(rules),
-            Schedules::SingleThread(func) => func.new_generation::<H>(rules)
-        }
-    }
-
-    fn clean_state(&self) -> bool {
-        match self {
-            Schedules::Async(rt) => rt.clean_state(),
-            Schedules::Pool(pool) => pool.clean_state(),
-            Schedules::SingleThread(func) => true,
-        }
-    }
-
-    fn schedule(&mut self, permute: Permutation)  {
-        match self {
-            Schedules::Async(rt) => rt.schedule(permute),
-            Schedules::Pool(pool) => pool.schedule(permute),
-            Schedules::SingleThread(func) => func.schedule(permute),
-        }
-    }
+/// deduplicates exactly in memory, whichever the scheduler
+pub fn schedules(scheduler: &SchedulerInput, workers: usize) -> Result<Schedules, Error> {
+    Ok(match scheduler {
+        SchedulerInput::AsyncRuntime => Schedules::Async(
+            TokioMutex::new()
+        ),
+        SchedulerInput::ThreadPool => Schedules::Pool(MultithreadMutex::new(workers)),
+        SchedulerInput::SingleThread => Schedules::SingleThread(SingleThread::new()),
+        SchedulerInput::WorkStealing => Schedules::Steal(WorkStealing::new(workers)?)
+    })
 }
 
-impl Length for Schedules {
-    fn length(&self) -> usize {
-        match self {
-            Schedules::Async(rt) => rt.length(),
-            Schedules::Pool(pool) => pool.length(),
-            Schedules::SingleThread(func) => func.length(),
-        }
-    }
+pub fn from_opt(opts: &Opt) -> Result<Schedules, Error> {
+    let storage = match (&opts.scheduler, &opts.dedup) {
+        (SchedulerInput::SingleThread, Dedup::Spill) => Storage::Spill {
+            budget: opts.memory_budget.checked_mul(1 << 20)
+                .ok_or(charsub::Error::CapacityOverflow)?,
+            dir: opts.spill_dir.clone()
+        },
+        (SchedulerInput::SingleThread, Dedup::Bloom) => Storage::Bloom {
+            capacity: opts.bloom_capacity,
+            error: opts.bloom_error,
+            retain: opts.checkpoint.is_some() || opts.order.is_buffered()
+        },
+        (scheduler, Dedup::Exact) if opts.spill_dir.is_none() => return schedules(scheduler, opts.workers),
+        (_, Dedup::Exact) => return Err(Error::BadInput("Expected --spill-dir only along with --dedup spill".into())),
+        (_, _) => return Err(Error::BadInput("Expected --dedup spill or bloom only with -s single".into()))
+    };
+    Ok(Schedules::new(1, &storage)?)
 }
-
-impl Into<HashSet<Cell>> for Schedules {
-    fn into(self) -> HashSet<Cell> {
-        match self {
-            Schedules::Async(rt) => rt.into(),
-            Schedules::Pool(pool) => pool.into(),
-            Schedules::SingleThread(func) => func.into(),
-        }
-    }
-}
\ No newline at end of file
@@ -0,0 +1,66 @@
+/// machine readable progress, for whatever watches a long run
+/// ---
+use charsub::progress::Progress;
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path
+};
+
+use serde::Serialize;
+
+/// one line of json, with `null` for whatever isn't known yet
+#[derive(Debug, Serialize)]
+struct Status {
+    generation: usize,
+    emitted: u128,
+    rate: f64,
+    keyspace: Option<u128>,
+    fraction: Option<f64>,
+    elapsed: f64,
+    eta: Option<f64>,
+}
+
+/// replaces the status file with the current progress,
+/// written aside first so readers never see half of it
+pub fn write_status(path: &Path, progress: &Progress, generation: usize) -> io::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+
+    let status = Status {
+        generation,
+        emitted: progress.emitted,
+        rate: progress.rate(),
+        keyspace: progress.keyspace,
+        fraction: progress.fraction(),
+        elapsed: progress.elapsed.as_secs_f64(),
+        eta: progress.eta().map(|x| x.as_secs_f64()),
+    };
+
+    let mut out = BufWriter::new(File::create(&partial)?);
+    serde_json::to_writer(&mut out, &status)?;
+    writeln!(out)?;
+    out.into_inner().map_err(io::IntoInnerError::into_error)?;
+    fs::rename(&partial, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn status_is_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("status.json");
+        let progress = Progress { emitted: 10, resumed: 0, keyspace: None, elapsed: Duration::from_secs(2) };
+        write_status(&path, &progress, 3).unwrap();
+
+        let status: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(status["generation"], 3);
+        assert_eq!(status["emitted"], 10);
+        assert_eq!(status["rate"], 5.0);
+        assert!(status["keyspace"].is_null() && status["eta"].is_null());
+    }
+}